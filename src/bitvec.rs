@@ -0,0 +1,490 @@
+use varisat::{CnfFormula, ExtendFormula, Lit};
+
+use crate::{add_at_most_one, add_exactly_one, const_lit, xor_gate};
+
+fn assert_same_width(a: &[Lit], b: &[Lit]) {
+    assert!(a.len() == b.len(), "bit vectors must have the same width");
+}
+//Returns the sum and carry bits of a + b + c
+fn full_adder(formula: &mut CnfFormula, a: Lit, b: Lit, c: Lit) -> (Lit, Lit) {
+    let sum = formula.new_lit();
+    let carry = formula.new_lit();
+    //The sum is true iff an odd number of the inputs are
+    for bits in 0..8u32 {
+        let lits: Vec<Lit> = [a, b, c]
+            .iter()
+            .enumerate()
+            .map(|(i, &lit)| if bits >> i & 1 == 1 { !lit } else { lit })
+            .collect();
+        let odd = bits.count_ones() % 2 == 1;
+        formula.add_clause(&[lits[0], lits[1], lits[2], if odd { sum } else { !sum }]);
+    }
+    //The carry is true iff at least two of the inputs are
+    for (x, y) in [(a, b), (a, c), (b, c)] {
+        formula.add_clause(&[!x, !y, carry]);
+        formula.add_clause(&[x, y, !carry]);
+    }
+    (sum, carry)
+}
+///Returns one literal per bit position, true iff a and b differ there
+fn differences(formula: &mut CnfFormula, a: &[Lit], b: &[Lit]) -> Vec<Lit> {
+    assert_same_width(a, b);
+    a.iter()
+        .zip(b)
+        .map(|(&a, &b)| xor_gate(formula, a, b))
+        .collect()
+}
+///Adds clauses requiring a and b to differ in exactly one bit, as consecutive
+///values of a Gray code do
+///Adds auxiliary variables: one xor_gate per bit, plus those of add_exactly_one
+pub fn add_gray_adjacent(formula: &mut CnfFormula, a: &[Lit], b: &[Lit]) {
+    let differences = differences(formula, a, b);
+    add_exactly_one(formula, &differences);
+}
+///Adds clauses allowing at most one bit to change between each pair of
+///consecutive frames, which must all have the same width
+///Adds auxiliary variables: one xor_gate per bit of each step, plus those of add_at_most_one
+pub fn add_at_most_one_transition(formula: &mut CnfFormula, frames: &[&[Lit]]) {
+    for pair in frames.windows(2) {
+        let differences = differences(formula, pair[0], pair[1]);
+        add_at_most_one(formula, &differences);
+    }
+}
+///Adds clauses requiring a and b to be equal if c is true, leaving them free otherwise
+///Adds no auxiliary variables
+pub fn add_conditional_equal(formula: &mut CnfFormula, c: Lit, a: &[Lit], b: &[Lit]) {
+    assert_same_width(a, b);
+    for (&a, &b) in a.iter().zip(b) {
+        formula.add_clause(&[!c, !a, b]);
+        formula.add_clause(&[!c, a, !b]);
+    }
+}
+///Adds clauses requiring a to be lexicographically at most b
+///The sequences are compared from their first literal on, false before true, as rows of
+///a matrix rather than little-endian numbers.
+///Adds auxiliary variables: one "equal so far" literal per position after the first
+pub fn add_lex_less_equal(formula: &mut CnfFormula, a: &[Lit], b: &[Lit]) {
+    assert_same_width(a, b);
+    //None stands for the empty prefix, which is always equal
+    let mut equal_so_far: Option<Lit> = None;
+    for (i, (&x, &y)) in a.iter().zip(b).enumerate() {
+        //The clause only applies while the prefix before position i is equal
+        let guarded = |lits: &[Lit]| -> Vec<Lit> {
+            let guard = equal_so_far.map(|equal| !equal);
+            guard.into_iter().chain(lits.iter().copied()).collect()
+        };
+        formula.add_clause(&guarded(&[!x, y]));
+        if i + 1 < a.len() {
+            let next = formula.new_lit();
+            formula.add_clause(&guarded(&[x, y, next]));
+            formula.add_clause(&guarded(&[!x, !y, next]));
+            equal_so_far = Some(next);
+        }
+    }
+}
+///Returns a bit vector equal to the value picked by the one-hot selector
+///selector[j] chooses values[j], all values must have the same width.
+///The selector is not required to be one-hot by this function.
+///Adds auxiliary variables: the returned bits
+pub fn select_value(formula: &mut CnfFormula, selector: &[Lit], values: &[&[Lit]]) -> Vec<Lit> {
+    assert!(
+        selector.len() == values.len(),
+        "one selector literal per value"
+    );
+    let width = values.first().map_or(0, |value| value.len());
+    for value in values {
+        assert!(value.len() == width, "bit vectors must have the same width");
+    }
+    let output: Vec<Lit> = formula.new_lit_iter(width).collect();
+    for (&chosen, value) in selector.iter().zip(values) {
+        for (&bit, &out) in value.iter().zip(&output) {
+            formula.add_clause(&[!chosen, !bit, out]);
+            formula.add_clause(&[!chosen, bit, !out]);
+        }
+    }
+    output
+}
+///Returns width little-endian bits forced to the value by unit clauses
+///Panics if the value does not fit in width bits.
+///Adds auxiliary variables: the returned bits
+pub fn const_bits(formula: &mut impl ExtendFormula, value: u64, width: usize) -> Vec<Lit> {
+    assert!(
+        width >= 64 || value >> width == 0,
+        "value does not fit in the width"
+    );
+    (0..width)
+        .map(|i| const_lit(formula, i < 64 && value >> i & 1 == 1))
+        .collect()
+}
+///Adds unit clauses forcing the little-endian bits to encode the value
+///Panics if the value does not fit in the bits.
+///Adds no auxiliary variables
+pub fn add_equals_int(formula: &mut CnfFormula, bits: &[Lit], value: u64) {
+    assert!(
+        bits.len() >= 64 || value >> bits.len() == 0,
+        "value does not fit in the width"
+    );
+    for (i, &bit) in bits.iter().enumerate() {
+        formula.add_clause(&[if i < 64 && value >> i & 1 == 1 {
+            bit
+        } else {
+            !bit
+        }]);
+    }
+}
+///Returns the unsigned integer the little-endian bits encode in the model
+///Bits missing from the model count as false. Panics on more than 64 bits.
+pub fn decode_binary(bits: &[Lit], model: &[Lit]) -> u64 {
+    assert!(bits.len() <= 64, "bit vector wider than 64 bits");
+    bits.iter()
+        .enumerate()
+        .filter(|(_, bit)| model.contains(bit))
+        .map(|(i, _)| 1 << i)
+        .sum()
+}
+///Returns the little-endian bits of a + b + cin and the carry out of the top bit
+///cin is added at the lowest bit, so adders can be chained by passing on the carry.
+///Adds auxiliary variables: a sum and a carry bit per position
+pub fn ripple_carry_add_cin(
+    formula: &mut CnfFormula,
+    a: &[Lit],
+    b: &[Lit],
+    cin: Lit,
+) -> (Vec<Lit>, Lit) {
+    assert_same_width(a, b);
+    let mut carry = cin;
+    let sum = a
+        .iter()
+        .zip(b)
+        .map(|(&a, &b)| {
+            let (sum, carry_out) = full_adder(formula, a, b, carry);
+            carry = carry_out;
+            sum
+        })
+        .collect();
+    (sum, carry)
+}
+///Returns the little-endian bits of a + b modulo 2 to the width, dropping the carry out
+///Adds auxiliary variables: those of ripple_carry_add_cin, plus a false carry in
+pub fn modular_add(formula: &mut CnfFormula, a: &[Lit], b: &[Lit]) -> Vec<Lit> {
+    let cin = const_lit(formula, false);
+    ripple_carry_add_cin(formula, a, b, cin).0
+}
+//Returns the bits of a - b modulo 2 to the width, and a literal true iff a >= b
+//This adds the complement of b plus one, a carry out means there was no borrow.
+fn subtract(formula: &mut CnfFormula, a: &[Lit], b: &[Lit]) -> (Vec<Lit>, Lit) {
+    let complement: Vec<Lit> = b.iter().map(|&bit| !bit).collect();
+    let cin = const_lit(formula, true);
+    ripple_carry_add_cin(formula, a, &complement, cin)
+}
+///Returns the little-endian bits of the unsigned absolute difference of a and b
+///Both differences are computed, the carry of a - b tells which one to select.
+///Adds auxiliary variables: those of two ripple_carry_add_cin, plus the returned bits
+pub fn abs_difference(formula: &mut CnfFormula, a: &[Lit], b: &[Lit]) -> Vec<Lit> {
+    let (a_minus_b, a_at_least_b) = subtract(formula, a, b);
+    let (b_minus_a, _) = subtract(formula, b, a);
+    select_value(
+        formula,
+        &[a_at_least_b, !a_at_least_b],
+        &[&a_minus_b, &b_minus_a],
+    )
+}
+///Returns a literal true iff the unsigned value of a is less than that of b
+///Adds auxiliary variables: those of a subtraction by ripple_carry_add_cin
+pub fn less_than(formula: &mut CnfFormula, a: &[Lit], b: &[Lit]) -> Lit {
+    !subtract(formula, a, b).1
+}
+///Adds clauses requiring each unsigned value to be less than the next one
+///Adds auxiliary variables: those of less_than per consecutive pair
+pub fn add_strictly_increasing(formula: &mut CnfFormula, values: &[&[Lit]]) {
+    for pair in values.windows(2) {
+        let less = less_than(formula, pair[0], pair[1]);
+        formula.add_clause(&[less]);
+    }
+}
+//Folds the values with a comparator and a mux per value after the first
+fn extreme_value(formula: &mut CnfFormula, values: &[&[Lit]], largest: bool) -> Vec<Lit> {
+    let (first, rest) = values.split_first().expect("needs at least one value");
+    let mut current = first.to_vec();
+    for &value in rest {
+        let (_, current_at_least) = subtract(formula, &current, value);
+        let take_value = if largest {
+            !current_at_least
+        } else {
+            current_at_least
+        };
+        current = select_value(formula, &[!take_value, take_value], &[&current, value]);
+    }
+    current
+}
+///Returns the little-endian bits of the smallest of the unsigned values
+///All values must have the same width, panics if there are none.
+///Adds auxiliary variables: a comparator and the selected bits per value after the first
+pub fn bitvec_min(formula: &mut CnfFormula, values: &[&[Lit]]) -> Vec<Lit> {
+    extreme_value(formula, values, false)
+}
+///Returns the little-endian bits of the largest of the unsigned values
+///All values must have the same width, panics if there are none.
+///Adds auxiliary variables: a comparator and the selected bits per value after the first
+pub fn bitvec_max(formula: &mut CnfFormula, values: &[&[Lit]]) -> Vec<Lit> {
+    extreme_value(formula, values, true)
+}
+#[cfg(test)]
+mod tests {
+    use varisat::{CnfFormula, ExtendFormula, Lit};
+
+    use crate::test_util::solve;
+    use crate::{
+        abs_difference, add_at_most_one_transition, add_conditional_equal, add_equals_int,
+        add_exactly_one, add_gray_adjacent, add_lex_less_equal, add_strictly_increasing,
+        bitvec_max, bitvec_min, const_bits, decode_binary, less_than, modular_add,
+        ripple_carry_add_cin, select_value,
+    };
+    //Forces the little-endian bits to the given value
+    fn force(formula: &mut CnfFormula, bits: &[Lit], value: u64) {
+        for (i, &bit) in bits.iter().enumerate() {
+            formula.add_clause(&[if value >> i & 1 == 1 { bit } else { !bit }]);
+        }
+    }
+    #[test]
+    fn one_transition_per_step() {
+        let mut formula = CnfFormula::new();
+        let frames: Vec<Vec<Lit>> = (0..3).map(|_| formula.new_lit_iter(4).collect()).collect();
+        let slices: Vec<&[Lit]> = frames.iter().map(|frame| &frame[..]).collect();
+        add_at_most_one_transition(&mut formula, &slices);
+        force(&mut formula, &frames[0], 0b0000);
+        let mut path = CnfFormula::from(formula.iter());
+        force(&mut path, &frames[2], 0b0011);
+        let model = solve(&path).unwrap();
+        let middle = frames[1].iter().filter(|bit| model.contains(bit)).count();
+        assert_eq!(middle, 1);
+        let mut too_far = CnfFormula::from(formula.iter());
+        force(&mut too_far, &frames[2], 0b0111);
+        assert!(solve(&too_far).is_none());
+        force(&mut formula, &frames[1], 0b0011);
+        assert!(solve(&formula).is_none());
+    }
+    #[test]
+    fn gray_adjacent() {
+        for (a_value, b_value, adjacent) in
+            [(2, 3, true), (2, 6, true), (2, 1, false), (5, 5, false)]
+        {
+            let mut formula = CnfFormula::new();
+            let a: Vec<Lit> = formula.new_lit_iter(3).collect();
+            let b: Vec<Lit> = formula.new_lit_iter(3).collect();
+            add_gray_adjacent(&mut formula, &a, &b);
+            force(&mut formula, &a, a_value);
+            force(&mut formula, &b, b_value);
+            assert_eq!(solve(&formula).is_some(), adjacent);
+        }
+    }
+    #[test]
+    fn select_constant() {
+        let constants = [0b1010, 0b0111, 0b1100];
+        for chosen in 0..3 {
+            let mut formula = CnfFormula::new();
+            let values: Vec<Vec<Lit>> = (0..3).map(|_| formula.new_lit_iter(4).collect()).collect();
+            for (value, &constant) in values.iter().zip(&constants) {
+                force(&mut formula, value, constant);
+            }
+            let selector: Vec<Lit> = formula.new_lit_iter(3).collect();
+            add_exactly_one(&mut formula, &selector);
+            formula.add_clause(&[selector[chosen]]);
+            let slices: Vec<&[Lit]> = values.iter().map(|value| &value[..]).collect();
+            let output = select_value(&mut formula, &selector, &slices);
+            let mut other = CnfFormula::from(formula.iter());
+            force(&mut other, &output, constants[(chosen + 1) % 3]);
+            assert!(solve(&other).is_none());
+            force(&mut formula, &output, constants[chosen]);
+            assert!(solve(&formula).is_some());
+        }
+    }
+    #[test]
+    fn constant_bits() {
+        let mut formula = CnfFormula::new();
+        let bits = const_bits(&mut formula, 5, 4);
+        let model = solve(&formula).unwrap();
+        let values: Vec<bool> = bits.iter().map(|bit| model.contains(bit)).collect();
+        assert_eq!(values, vec![true, false, true, false]);
+        assert_eq!(decode_binary(&bits, &model), 5);
+        assert_eq!(const_bits(&mut formula, 0, 0), vec![]);
+    }
+    #[test]
+    #[should_panic]
+    fn constant_too_wide() {
+        const_bits(&mut CnfFormula::new(), 16, 4);
+    }
+    #[test]
+    fn decode_constant() {
+        let mut formula = CnfFormula::new();
+        let bits = const_bits(&mut formula, 42, 8);
+        let model = solve(&formula).unwrap();
+        assert_eq!(decode_binary(&bits, &model), 42);
+        assert_eq!(decode_binary(&[], &model), 0);
+    }
+    #[test]
+    fn conditional_equal() {
+        for (condition, a_value, b_value, allowed) in [
+            (true, 9, 9, true),
+            (true, 9, 8, false),
+            (false, 9, 8, true),
+            (false, 3, 3, true),
+        ] {
+            let mut formula = CnfFormula::new();
+            let c = formula.new_lit();
+            let a: Vec<Lit> = formula.new_lit_iter(4).collect();
+            let b: Vec<Lit> = formula.new_lit_iter(4).collect();
+            add_conditional_equal(&mut formula, c, &a, &b);
+            formula.add_clause(&[if condition { c } else { !c }]);
+            force(&mut formula, &a, a_value);
+            force(&mut formula, &b, b_value);
+            assert_eq!(solve(&formula).is_some(), allowed);
+        }
+    }
+    #[test]
+    fn lex_order() {
+        for a_value in 0..8u64 {
+            for b_value in 0..8u64 {
+                let mut formula = CnfFormula::new();
+                let a: Vec<Lit> = formula.new_lit_iter(3).collect();
+                let b: Vec<Lit> = formula.new_lit_iter(3).collect();
+                add_lex_less_equal(&mut formula, &a, &b);
+                //Lexicographic from the first literal is numeric order when it is the top bit
+                let reversed = |bits: &[Lit]| -> Vec<Lit> { bits.iter().rev().copied().collect() };
+                force(&mut formula, &reversed(&a), a_value);
+                force(&mut formula, &reversed(&b), b_value);
+                assert_eq!(solve(&formula).is_some(), a_value <= b_value);
+            }
+        }
+    }
+    #[test]
+    fn add_with_carry_in() {
+        for (a_value, b_value, carry_in) in
+            [(7, 8, true), (7, 8, false), (15, 15, true), (0, 0, false)]
+        {
+            let mut formula = CnfFormula::new();
+            let a = const_bits(&mut formula, a_value, 4);
+            let b = const_bits(&mut formula, b_value, 4);
+            let cin = formula.new_lit();
+            formula.add_clause(&[if carry_in { cin } else { !cin }]);
+            let (mut sum, carry) = ripple_carry_add_cin(&mut formula, &a, &b, cin);
+            sum.push(carry);
+            let model = solve(&formula).unwrap();
+            assert_eq!(
+                decode_binary(&sum, &model),
+                a_value + b_value + u64::from(carry_in)
+            );
+        }
+    }
+    #[test]
+    fn equals_int() {
+        let mut formula = CnfFormula::new();
+        let bits: Vec<Lit> = formula.new_lit_iter(8).collect();
+        add_equals_int(&mut formula, &bits, 42);
+        let model = solve(&formula).unwrap();
+        assert_eq!(decode_binary(&bits, &model), 42);
+        add_equals_int(&mut formula, &bits[..4], 10);
+        assert!(solve(&formula).is_some());
+        add_equals_int(&mut formula, &bits[..4], 11);
+        assert!(solve(&formula).is_none());
+    }
+    #[test]
+    #[should_panic]
+    fn equals_int_too_wide() {
+        let mut formula = CnfFormula::new();
+        let bits: Vec<Lit> = formula.new_lit_iter(8).collect();
+        add_equals_int(&mut formula, &bits, 300);
+    }
+    #[test]
+    fn wrapping_add() {
+        for (a_value, b_value) in [(15, 1), (8, 8), (3, 4), (9, 12)] {
+            let mut formula = CnfFormula::new();
+            let a = const_bits(&mut formula, a_value, 4);
+            let b = const_bits(&mut formula, b_value, 4);
+            let sum = modular_add(&mut formula, &a, &b);
+            assert_eq!(sum.len(), 4);
+            let model = solve(&formula).unwrap();
+            assert_eq!(decode_binary(&sum, &model), (a_value + b_value) % 16);
+        }
+    }
+    #[test]
+    fn absolute_difference() {
+        for a_value in 0..8u64 {
+            for b_value in 0..8u64 {
+                let mut formula = CnfFormula::new();
+                let a = const_bits(&mut formula, a_value, 3);
+                let b = const_bits(&mut formula, b_value, 3);
+                let difference = abs_difference(&mut formula, &a, &b);
+                let model = solve(&formula).unwrap();
+                assert_eq!(
+                    decode_binary(&difference, &model),
+                    a_value.abs_diff(b_value)
+                );
+            }
+        }
+        for (a_value, b_value) in [(3, 7), (7, 3)] {
+            let mut formula = CnfFormula::new();
+            let a = const_bits(&mut formula, a_value, 4);
+            let b = const_bits(&mut formula, b_value, 4);
+            let difference = abs_difference(&mut formula, &a, &b);
+            let model = solve(&formula).unwrap();
+            assert_eq!(decode_binary(&difference, &model), 4);
+        }
+    }
+    #[test]
+    fn min_and_max() {
+        for (constants, min, max) in [
+            (vec![5, 2, 9], 2, 9),
+            (vec![9, 5, 2], 2, 9),
+            (vec![7, 7], 7, 7),
+            (vec![4], 4, 4),
+        ] {
+            let mut formula = CnfFormula::new();
+            let values: Vec<Vec<Lit>> = constants
+                .iter()
+                .map(|&constant| const_bits(&mut formula, constant, 4))
+                .collect();
+            let slices: Vec<&[Lit]> = values.iter().map(|value| &value[..]).collect();
+            let smallest = bitvec_min(&mut formula, &slices);
+            let largest = bitvec_max(&mut formula, &slices);
+            let model = solve(&formula).unwrap();
+            assert_eq!(decode_binary(&smallest, &model), min);
+            assert_eq!(decode_binary(&largest, &model), max);
+        }
+    }
+    #[test]
+    fn strictly_increasing() {
+        let mut formula = CnfFormula::new();
+        let values: Vec<Vec<Lit>> = (0..3).map(|_| formula.new_lit_iter(4).collect()).collect();
+        let slices: Vec<&[Lit]> = values.iter().map(|value| &value[..]).collect();
+        add_strictly_increasing(&mut formula, &slices);
+        let model = solve(&formula).unwrap();
+        let decoded: Vec<u64> = values
+            .iter()
+            .map(|value| decode_binary(value, &model))
+            .collect();
+        assert!(decoded[0] < decoded[1] && decoded[1] < decoded[2]);
+        let mut increasing = CnfFormula::from(formula.iter());
+        force(&mut increasing, &values[0], 3);
+        force(&mut increasing, &values[2], 5);
+        assert!(solve(&increasing).is_some());
+        force(&mut increasing, &values[1], 4);
+        assert!(solve(&increasing).is_some());
+        force(&mut formula, &values[0], 6);
+        force(&mut formula, &values[1], 6);
+        assert!(solve(&formula).is_none());
+    }
+    #[test]
+    fn less_than_exhaustive() {
+        for a_value in 0..8u64 {
+            for b_value in 0..8u64 {
+                let mut formula = CnfFormula::new();
+                let a = const_bits(&mut formula, a_value, 3);
+                let b = const_bits(&mut formula, b_value, 3);
+                let less = less_than(&mut formula, &a, &b);
+                let model = solve(&formula).unwrap();
+                assert_eq!(model.contains(&less), a_value < b_value);
+            }
+        }
+    }
+}
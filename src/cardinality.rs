@@ -0,0 +1,674 @@
+use varisat::{CnfFormula, ExtendFormula, Lit};
+
+use crate::{add_parity, and_gate, exactly_k, make_sorting_network, or_gate, sort_swap};
+
+///Returns a literal that is true iff at least k of the inputs are true
+///Nothing is forced on the inputs.
+///Adds auxiliary variables: those of a sorting network over the inputs
+pub fn threshold_gate(formula: &mut CnfFormula, inputs: &[Lit], k: usize) -> Lit {
+    let n = inputs.len();
+    if k == 0 {
+        return and_gate(formula, &[]);
+    }
+    if k > n {
+        return or_gate(formula, &[]);
+    }
+    //The sorted inputs are ascending, so the last k are all true iff at least k inputs are
+    let sorted = make_sorting_network(formula, inputs);
+    sorted[n - k]
+}
+///Returns a literal that is true iff more than half of the inputs are true
+///With an even number of inputs a tie counts as false.
+///Adds auxiliary variables: those of a sorting network over the inputs
+pub fn majority_gate(formula: &mut CnfFormula, inputs: &[Lit]) -> Lit {
+    threshold_gate(formula, inputs, inputs.len() / 2 + 1)
+}
+///Adds clauses requiring at most at_most or at least at_least of the inputs to be true,
+///ruling out the counts strictly between the two
+///Adds auxiliary variables: those of a sorting network over the inputs
+pub fn add_at_most_or_at_least(
+    formula: &mut CnfFormula,
+    vars: &[Lit],
+    at_most: usize,
+    at_least: usize,
+) {
+    assert!(at_most < at_least, "at_most must be less than at_least");
+    let n = vars.len();
+    if at_most >= n {
+        return;
+    }
+    let sorted = make_sorting_network(formula, vars);
+    //More than at_most true implies at least at_least true
+    let mut clause = vec![!sorted[n - at_most - 1]];
+    if at_least <= n {
+        clause.push(sorted[n - at_least]);
+    }
+    formula.add_clause(&clause);
+}
+///Adds clauses requiring at least k of the inputs to be true
+///Adds auxiliary variables: those of a sorting network over the inputs
+pub fn add_at_least_k(formula: &mut CnfFormula, vars: &[Lit], k: usize) {
+    let n = vars.len();
+    if k == 0 {
+        return;
+    }
+    if k > n {
+        formula.add_clause(&[]);
+        return;
+    }
+    let sorted = make_sorting_network(formula, vars);
+    formula.add_clause(&[sorted[n - k]]);
+}
+///Adds clauses requiring at most k of the inputs to be true
+///Adds auxiliary variables: those of a sorting network over the inputs
+pub fn add_at_most_k(formula: &mut CnfFormula, vars: &[Lit], k: usize) {
+    let n = vars.len();
+    if k >= n {
+        return;
+    }
+    let sorted = make_sorting_network(formula, vars);
+    formula.add_clause(&[!sorted[n - k - 1]]);
+}
+///Adds clauses requiring at most k of the inputs to be true
+///This uses the sequential counter of Sinz, which keeps a unary count of the true
+///inputs seen so far, capped at k.
+///Adds auxiliary variables: k per input except the last
+pub fn add_at_most_k_seqcounter(formula: &mut CnfFormula, vars: &[Lit], k: usize) {
+    let n = vars.len();
+    if k >= n {
+        return;
+    }
+    //prev[j] is implied by at least j + 1 of the inputs before the current one being true
+    let mut prev: Vec<Lit> = Vec::new();
+    for (i, &var) in vars.iter().enumerate() {
+        if k == 0 || prev.len() == k {
+            //The count is already at k, one more true input would overflow it
+            let mut clause = vec![!var];
+            clause.extend(prev.last().map(|&full| !full));
+            formula.add_clause(&clause);
+        }
+        if i + 1 == n || k == 0 {
+            continue;
+        }
+        let cur: Vec<Lit> = formula.new_lit_iter(k).collect();
+        for j in 0..k {
+            if j < prev.len() {
+                formula.add_clause(&[!prev[j], cur[j]]);
+            }
+            if j == 0 {
+                formula.add_clause(&[!var, cur[0]]);
+            } else if j <= prev.len() {
+                formula.add_clause(&[!var, !prev[j - 1], cur[j]]);
+            }
+        }
+        prev = cur;
+    }
+}
+///Adds clauses requiring at least k of the inputs to be true
+///This is the sequential counter requiring at most n - k of the negated inputs to be true,
+///which is small when n - k is.
+///Adds auxiliary variables: n - k per input except the last
+pub fn add_at_least_k_seqcounter(formula: &mut CnfFormula, vars: &[Lit], k: usize) {
+    let n = vars.len();
+    if k > n {
+        formula.add_clause(&[]);
+        return;
+    }
+    let negated: Vec<Lit> = vars.iter().map(|&var| !var).collect();
+    add_at_most_k_seqcounter(formula, &negated, n - k);
+}
+///Adds clauses requiring the number of true inputs to be even, or odd if even is false
+///The count is odd exactly when the xor of the inputs is true, so this is add_parity.
+///Adds auxiliary variables: those of add_parity
+pub fn add_count_parity(formula: &mut CnfFormula, vars: &[Lit], even: bool) {
+    add_parity(formula, vars, !even);
+}
+///Adds clauses requiring exactly k of the groups to have at least one true literal
+///Adds auxiliary variables: one "group non-empty" literal per group, plus those of exactly_k
+pub fn add_k_nonempty_groups(formula: &mut CnfFormula, groups: &[&[Lit]], k: usize) {
+    assert!(k <= groups.len(), "fewer than k groups");
+    let nonempty: Vec<Lit> = groups.iter().map(|group| or_gate(formula, group)).collect();
+    exactly_k(formula, &nonempty, k);
+}
+///Adds clauses requiring exactly per_group[i] literals of groups[i] to be true for each i
+///When the groups are disjoint this is cheaper than one constraint over their union, as
+///each group gets its own exactly_k.
+///Adds auxiliary variables: those of exactly_k per group
+pub fn exactly_k_partitioned(formula: &mut CnfFormula, groups: &[&[Lit]], per_group: &[usize]) {
+    assert!(groups.len() == per_group.len(), "one count per group");
+    for (&group, &k) in groups.iter().zip(per_group) {
+        assert!(k <= group.len(), "group has fewer than k literals");
+        exactly_k(formula, group, k);
+    }
+}
+//Returns the largest m values of the inputs in descending order, as the outputs of an
+//odd-even merge network whose merges are all cut down to their top m outputs
+fn cardinality_network(formula: &mut CnfFormula, vars: &[Lit], m: usize) -> Vec<Lit> {
+    if vars.len() <= 1 {
+        return vars[..vars.len().min(m)].to_vec();
+    }
+    let (left, right) = vars.split_at(vars.len() / 2);
+    let left = cardinality_network(formula, left, m);
+    let right = cardinality_network(formula, right, m);
+    let mut merged = odd_even_merge(formula, &left, &right);
+    merged.truncate(m);
+    merged
+}
+//Merges two descending sequences into one
+fn odd_even_merge(formula: &mut CnfFormula, a: &[Lit], b: &[Lit]) -> Vec<Lit> {
+    match (a.len(), b.len()) {
+        (0, _) => return b.to_vec(),
+        (_, 0) => return a.to_vec(),
+        (1, 1) => {
+            let (min, max) = sort_swap(formula, a[0], b[0]);
+            return vec![max, min];
+        }
+        _ => {}
+    }
+    let evens = |seq: &[Lit]| -> Vec<Lit> { seq.iter().step_by(2).copied().collect() };
+    let odds = |seq: &[Lit]| -> Vec<Lit> { seq.iter().skip(1).step_by(2).copied().collect() };
+    let v = odd_even_merge(formula, &evens(a), &evens(b));
+    let w = odd_even_merge(formula, &odds(a), &odds(b));
+    //v has as many elements as w or up to two more, its first is the overall maximum
+    let mut merged = vec![v[0]];
+    for i in 0..w.len() {
+        if i + 1 < v.len() {
+            let (min, max) = sort_swap(formula, w[i], v[i + 1]);
+            merged.push(max);
+            merged.push(min);
+        } else {
+            merged.push(w[i]);
+        }
+    }
+    merged.extend_from_slice(&v[(w.len() + 1).min(v.len())..]);
+    merged
+}
+//Returns the unary count of the true inputs capped at m: output j is true iff
+//at least j + 1 inputs are, as a totalizer tree of unary adders
+fn totalizer(formula: &mut CnfFormula, vars: &[Lit], m: usize) -> Vec<Lit> {
+    if vars.len() <= 1 {
+        return vars[..vars.len().min(m)].to_vec();
+    }
+    let (left, right) = vars.split_at(vars.len() / 2);
+    let a = totalizer(formula, left, m);
+    let b = totalizer(formula, right, m);
+    unary_add(formula, &a, &b, m)
+}
+//Returns the unary sum of two unary counts capped at m
+fn unary_add(formula: &mut CnfFormula, a: &[Lit], b: &[Lit], m: usize) -> Vec<Lit> {
+    let sum: Vec<Lit> = formula.new_lit_iter((a.len() + b.len()).min(m)).collect();
+    for i in 0..=a.len() {
+        for j in 0..=b.len() {
+            //At least i from a and j from b make at least i + j
+            if i + j > 0 {
+                let mut clause = vec![sum[(i + j).min(sum.len()) - 1]];
+                clause.extend(i.checked_sub(1).map(|i| !a[i]));
+                clause.extend(j.checked_sub(1).map(|j| !b[j]));
+                formula.add_clause(&clause);
+            }
+            //At most i from a and j from b make at most i + j
+            if i + j < sum.len() {
+                let mut clause = vec![!sum[i + j]];
+                clause.extend(a.get(i).copied());
+                clause.extend(b.get(j).copied());
+                formula.add_clause(&clause);
+            }
+        }
+    }
+    sum
+}
+///An at-most-k constraint that more variables can be added to later
+///The variables are counted by a forest of totalizer subtrees: each added variable starts
+///a subtree of its own, and two subtrees over the same number of variables are merged, as
+///in a binary counter. The running count of each prefix of the forest is kept, so an added
+///variable only adds the count up to its subtree and bounds that by k.
+#[derive(Debug, Clone)]
+pub struct AtMostK {
+    k: usize,
+    //The subtrees by number of variables, largest first, with their counts capped at k + 1
+    trees: Vec<(usize, Vec<Lit>)>,
+    //The count of the variables in the subtrees up to and including each one
+    totals: Vec<Vec<Lit>>,
+}
+impl AtMostK {
+    ///Adds clauses requiring at most k of the variables to be true
+    ///Adds auxiliary variables: at most 2(k + 1) per variable
+    pub fn new(formula: &mut CnfFormula, vars: &[Lit], k: usize) -> AtMostK {
+        let mut at_most = AtMostK {
+            k,
+            trees: Vec::new(),
+            totals: Vec::new(),
+        };
+        for &var in vars {
+            at_most.add_variable(formula, var);
+        }
+        at_most
+    }
+    ///Extends the constraint to the variable, adding it to the count
+    ///Adds auxiliary variables: at most k + 1 per subtree merge and k + 1 for the count, so at
+    ///most 2(k + 1) per variable over a run of additions
+    pub fn add_variable(&mut self, formula: &mut CnfFormula, lit: Lit) {
+        let (mut leaves, mut count) = (1, vec![lit]);
+        while let Some(&(other, _)) = self.trees.last() {
+            if other != leaves {
+                break;
+            }
+            let (_, other_count) = self.trees.pop().unwrap();
+            self.totals.pop();
+            leaves += other;
+            count = unary_add(formula, &other_count, &count, self.k + 1);
+        }
+        let total = match self.totals.last() {
+            Some(total) => unary_add(formula, total, &count, self.k + 1),
+            None => count.clone(),
+        };
+        if let Some(&over) = total.get(self.k) {
+            formula.add_clause(&[!over]);
+        }
+        self.trees.push((leaves, count));
+        self.totals.push(total);
+    }
+}
+///A sorting network over a set of variables that any number of cardinality constraints
+///on them can share
+///The network is built once, each constraint then only adds unit clauses on its outputs.
+#[derive(Debug, Clone)]
+pub struct SharedCounter {
+    //Ascending, sorted[n - k] is true iff at least k of the variables are
+    sorted: Vec<Lit>,
+}
+impl SharedCounter {
+    ///Builds the sorting network over the variables
+    ///Adds auxiliary variables: those of a sorting network over the variables
+    pub fn new(formula: &mut CnfFormula, vars: &[Lit]) -> SharedCounter {
+        SharedCounter {
+            sorted: make_sorting_network(formula, vars),
+        }
+    }
+    ///Adds clauses requiring at least k of the variables to be true
+    ///Adds no auxiliary variables
+    pub fn at_least(&self, formula: &mut CnfFormula, k: usize) {
+        let n = self.sorted.len();
+        if k == 0 {
+            return;
+        }
+        if k > n {
+            formula.add_clause(&[]);
+            return;
+        }
+        formula.add_clause(&[self.sorted[n - k]]);
+    }
+    ///Adds clauses requiring at most k of the variables to be true
+    ///Adds no auxiliary variables
+    pub fn at_most(&self, formula: &mut CnfFormula, k: usize) {
+        let n = self.sorted.len();
+        if k < n {
+            formula.add_clause(&[!self.sorted[n - k - 1]]);
+        }
+    }
+    ///Adds clauses requiring exactly k of the variables to be true
+    ///Adds no auxiliary variables
+    pub fn exactly(&self, formula: &mut CnfFormula, k: usize) {
+        self.at_least(formula, k);
+        self.at_most(formula, k);
+    }
+}
+///The exactly-k encodings of this crate, to choose one per call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EkEncoding {
+    ///exactly_k, a full sorting network over the inputs
+    SortingNetwork,
+    ///An odd-even merge network keeping only the top k + 1 outputs of each merge
+    CardinalityNetwork,
+    ///A totalizer tree of unary adders, each keeping only counts up to k + 1
+    Totalizer,
+    ///add_at_most_k_seqcounter and add_at_least_k_seqcounter
+    SequentialCounter,
+}
+///Adds clauses requiring exactly k of the input variables to be true using the given encoding
+///All encodings fix the inputs by unit clauses when k is 0 or the input length.
+///Adds auxiliary variables: those of the chosen encoding, none when k is 0 or the input length
+pub fn exactly_k_with(formula: &mut CnfFormula, vars: &[Lit], k: usize, enc: EkEncoding) {
+    let n = vars.len();
+    assert!(k <= n, "k exceeds the number of inputs");
+    if k == 0 || k == n {
+        for &var in vars {
+            formula.add_clause(&[if k == 0 { !var } else { var }]);
+        }
+        return;
+    }
+    let counts = match enc {
+        EkEncoding::SortingNetwork => return exactly_k(formula, vars, k),
+        EkEncoding::SequentialCounter => {
+            add_at_most_k_seqcounter(formula, vars, k);
+            add_at_least_k_seqcounter(formula, vars, k);
+            return;
+        }
+        EkEncoding::CardinalityNetwork => cardinality_network(formula, vars, k + 1),
+        EkEncoding::Totalizer => totalizer(formula, vars, k + 1),
+    };
+    //counts[j] is true iff at least j + 1 inputs are
+    formula.add_clause(&[counts[k - 1]]);
+    formula.add_clause(&[!counts[k]]);
+}
+#[cfg(test)]
+mod tests {
+    use varisat::{CnfFormula, ExtendFormula, Lit, Solver};
+
+    use crate::test_util::truth_table;
+    use crate::{
+        add_at_least_k, add_at_least_k_seqcounter, add_at_most_k, add_at_most_k_seqcounter,
+        add_at_most_or_at_least, add_count_parity, add_k_nonempty_groups, clause_count,
+        exactly_k_partitioned, exactly_k_with, majority_gate, threshold_gate, AtMostK, EkEncoding,
+        SharedCounter,
+    };
+    fn count_at_least(n: usize, k: u32) -> Vec<bool> {
+        (0..1u32 << n).map(|bits| bits.count_ones() >= k).collect()
+    }
+    #[test]
+    fn threshold_tracks_count() {
+        for k in 0..=5 {
+            assert_eq!(
+                truth_table(4, |formula, lits| threshold_gate(formula, lits, k)),
+                count_at_least(4, k as u32),
+                "k={}",
+                k
+            );
+        }
+    }
+    //Returns for each count whether the constraint allows exactly that many true inputs
+    fn allowed_counts(n: usize, build: impl Fn(&mut CnfFormula, &[Lit])) -> Vec<bool> {
+        (0..=n)
+            .map(|count| {
+                let mut formula = CnfFormula::new();
+                let lits: Vec<Lit> = formula.new_lit_iter(n).collect();
+                build(&mut formula, &lits);
+                for (i, &lit) in lits.iter().enumerate() {
+                    formula.add_clause(&[if i < count { lit } else { !lit }]);
+                }
+                let mut solver = Solver::new();
+                solver.add_formula(&formula);
+                solver.solve().unwrap()
+            })
+            .collect()
+    }
+    #[test]
+    fn at_most_or_at_least() {
+        assert_eq!(
+            allowed_counts(6, |formula, lits| add_at_most_or_at_least(
+                formula, lits, 1, 4
+            )),
+            vec![true, true, false, false, true, true, true]
+        );
+        assert_eq!(
+            allowed_counts(4, |formula, lits| add_at_most_or_at_least(
+                formula, lits, 2, 7
+            )),
+            vec![true, true, true, false, false]
+        );
+        assert_eq!(
+            allowed_counts(3, |formula, lits| add_at_most_or_at_least(
+                formula, lits, 3, 4
+            )),
+            vec![true; 4]
+        );
+    }
+    #[test]
+    fn majority_of_3() {
+        assert_eq!(truth_table(3, majority_gate), count_at_least(3, 2));
+    }
+    #[test]
+    fn majority_tie() {
+        assert_eq!(truth_table(4, majority_gate), count_at_least(4, 3));
+        assert_eq!(truth_table(1, majority_gate), count_at_least(1, 1));
+        assert_eq!(truth_table(0, majority_gate), vec![false]);
+    }
+    //Returns for every assignment of the inputs whether the constraint allows it
+    fn allowed_assignments(n: usize, build: impl Fn(&mut CnfFormula, &[Lit])) -> Vec<bool> {
+        let mut formula = CnfFormula::new();
+        let lits: Vec<Lit> = formula.new_lit_iter(n).collect();
+        build(&mut formula, &lits);
+        let mut solver = Solver::new();
+        solver.add_formula(&formula);
+        (0..1u32 << n)
+            .map(|bits| {
+                let assignment: Vec<Lit> = lits
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &lit)| if bits >> i & 1 == 1 { lit } else { !lit })
+                    .collect();
+                solver.assume(&assignment);
+                solver.solve().unwrap()
+            })
+            .collect()
+    }
+    #[test]
+    fn seqcounter_at_most() {
+        for n in 0..=6 {
+            for k in 0..=n + 1 {
+                let expected: Vec<bool> = (0..1u32 << n)
+                    .map(|bits| bits.count_ones() as usize <= k)
+                    .collect();
+                assert_eq!(
+                    allowed_assignments(n, |formula, lits| add_at_most_k(formula, lits, k)),
+                    expected,
+                    "n={} k={}",
+                    n,
+                    k
+                );
+                assert_eq!(
+                    allowed_assignments(n, |formula, lits| add_at_most_k_seqcounter(
+                        formula, lits, k
+                    )),
+                    expected,
+                    "n={} k={}",
+                    n,
+                    k
+                );
+            }
+        }
+    }
+    #[test]
+    fn seqcounter_matches_network() {
+        for n in 0..=7 {
+            for k in 0..=n + 1 {
+                let network =
+                    allowed_assignments(n, |formula, lits| add_at_least_k(formula, lits, k));
+                let expected: Vec<bool> = (0..1u32 << n)
+                    .map(|bits| bits.count_ones() as usize >= k)
+                    .collect();
+                assert_eq!(network, expected, "n={} k={}", n, k);
+                assert_eq!(
+                    allowed_assignments(n, |formula, lits| add_at_least_k_seqcounter(
+                        formula, lits, k
+                    )),
+                    network,
+                    "n={} k={}",
+                    n,
+                    k
+                );
+            }
+        }
+    }
+    const EK_ENCODINGS: [EkEncoding; 4] = [
+        EkEncoding::SortingNetwork,
+        EkEncoding::CardinalityNetwork,
+        EkEncoding::Totalizer,
+        EkEncoding::SequentialCounter,
+    ];
+    #[test]
+    fn ek_encodings_exhaustive() {
+        for enc in EK_ENCODINGS {
+            for n in 0..=7 {
+                for k in 0..=n {
+                    let expected: Vec<bool> = (0..1u32 << n)
+                        .map(|bits| bits.count_ones() as usize == k)
+                        .collect();
+                    assert_eq!(
+                        allowed_assignments(n, |formula, lits| exactly_k_with(
+                            formula, lits, k, enc
+                        )),
+                        expected,
+                        "{:?} n={} k={}",
+                        enc,
+                        n,
+                        k
+                    );
+                }
+            }
+        }
+    }
+    #[test]
+    fn ek_encodings_agree() {
+        let forced: [&[usize]; 5] = [&[], &[0, 11], &[1, 2, 3, 4], &[0, 3, 6, 9, 11], &[5]];
+        for true_inputs in forced {
+            let results: Vec<bool> = EK_ENCODINGS
+                .iter()
+                .map(|&enc| {
+                    let mut formula = CnfFormula::new();
+                    let lits: Vec<Lit> = formula.new_lit_iter(12).collect();
+                    exactly_k_with(&mut formula, &lits, 4, enc);
+                    for &i in true_inputs {
+                        formula.add_clause(&[lits[i]]);
+                    }
+                    formula.add_clause(&[!lits[7]]);
+                    let mut solver = Solver::new();
+                    solver.add_formula(&formula);
+                    solver.solve().unwrap()
+                })
+                .collect();
+            assert_eq!(
+                results,
+                vec![true_inputs.len() <= 4; 4],
+                "{:?}",
+                true_inputs
+            );
+        }
+    }
+    #[test]
+    fn at_most_k_extended() {
+        let mut formula = CnfFormula::new();
+        let lits: Vec<Lit> = formula.new_lit_iter(5).collect();
+        let mut at_most = AtMostK::new(&mut formula, &lits[..3], 2);
+        for &lit in &lits[3..] {
+            at_most.add_variable(&mut formula, lit);
+        }
+        let mut solver = Solver::new();
+        solver.add_formula(&formula);
+        solver.assume(&[lits[0], lits[3], lits[4]]);
+        assert!(!solver.solve().unwrap());
+        solver.assume(&[lits[1], lits[4]]);
+        assert!(solver.solve().unwrap());
+        for n in 0..=5 {
+            for k in 0..=n {
+                let expected: Vec<bool> = (0..1u32 << n)
+                    .map(|bits| bits.count_ones() as usize <= k)
+                    .collect();
+                let allowed = allowed_assignments(n, |formula, lits| {
+                    let mut at_most = AtMostK::new(formula, &lits[..n / 2], k);
+                    for &lit in &lits[n / 2..] {
+                        at_most.add_variable(formula, lit);
+                    }
+                });
+                assert_eq!(allowed, expected, "n={} k={}", n, k);
+            }
+        }
+    }
+    #[test]
+    fn at_most_k_one_at_a_time() {
+        for k in [0, 1, 3] {
+            let expected: Vec<bool> = (0..1u32 << 9)
+                .map(|bits| bits.count_ones() as usize <= k)
+                .collect();
+            let allowed = allowed_assignments(9, |formula, lits| {
+                let var_count = formula.var_count();
+                let mut at_most = AtMostK::new(formula, &[], k);
+                for &lit in lits {
+                    at_most.add_variable(formula, lit);
+                }
+                assert!(formula.var_count() - var_count <= 2 * (k + 1) * lits.len());
+            });
+            assert_eq!(allowed, expected, "k={}", k);
+        }
+    }
+    #[test]
+    fn count_parity() {
+        for even in [true, false] {
+            let expected: Vec<bool> = (0..1u32 << 4)
+                .map(|bits| (bits.count_ones() % 2 == 0) == even)
+                .collect();
+            assert_eq!(
+                allowed_assignments(4, |formula, lits| add_count_parity(formula, lits, even)),
+                expected
+            );
+            assert_eq!(
+                allowed_counts(4, |formula, lits| add_count_parity(formula, lits, even))[3],
+                !even
+            );
+        }
+        assert_eq!(
+            allowed_assignments(0, |formula, lits| add_count_parity(formula, lits, false)),
+            vec![false]
+        );
+    }
+    #[test]
+    fn two_nonempty_groups() {
+        let allowed = allowed_assignments(8, |formula, lits| {
+            let groups: Vec<&[Lit]> = lits.chunks(2).collect();
+            add_k_nonempty_groups(formula, &groups, 2);
+        });
+        for (bits, &allowed) in allowed.iter().enumerate() {
+            let nonempty = (0..4).filter(|group| bits >> (2 * group) & 3 != 0).count();
+            assert_eq!(allowed, nonempty == 2, "{:08b}", bits);
+        }
+    }
+    #[test]
+    fn partitioned_counts() {
+        let allowed = allowed_assignments(6, |formula, lits| {
+            exactly_k_partitioned(formula, &[&lits[..3], &lits[3..]], &[1, 2]);
+        });
+        for (bits, &allowed) in allowed.iter().enumerate() {
+            let (first, second) = (bits & 0b111, bits >> 3);
+            assert_eq!(
+                allowed,
+                first.count_ones() == 1 && second.count_ones() == 2,
+                "{:06b}",
+                bits
+            );
+        }
+    }
+    #[test]
+    #[should_panic]
+    fn partitioned_count_mismatch() {
+        let mut formula = CnfFormula::new();
+        let lits: Vec<Lit> = formula.new_lit_iter(4).collect();
+        exactly_k_partitioned(&mut formula, &[&lits[..2], &lits[2..]], &[1]);
+    }
+    #[test]
+    fn shared_counter() {
+        let between = allowed_counts(6, |formula, lits| {
+            let counter = SharedCounter::new(formula, lits);
+            counter.at_most(formula, 3);
+            counter.at_least(formula, 2);
+        });
+        assert_eq!(between, vec![false, false, true, true, false, false, false]);
+        for k in 0..=7 {
+            let exactly = allowed_counts(6, |formula, lits| {
+                SharedCounter::new(formula, lits).exactly(formula, k)
+            });
+            let expected: Vec<bool> = (0..=6).map(|count| count == k).collect();
+            assert_eq!(exactly, expected, "k={}", k);
+        }
+        let mut shared = CnfFormula::new();
+        let lits: Vec<Lit> = shared.new_lit_iter(20).collect();
+        let counter = SharedCounter::new(&mut shared, &lits);
+        counter.at_most(&mut shared, 3);
+        counter.at_least(&mut shared, 2);
+        let mut separate = CnfFormula::new();
+        let lits: Vec<Lit> = separate.new_lit_iter(20).collect();
+        add_at_most_k(&mut separate, &lits, 3);
+        add_at_least_k(&mut separate, &lits, 2);
+        //One network instead of two plus the same two unit clauses
+        assert_eq!(2 * clause_count(&shared) - 2, clause_count(&separate));
+    }
+}
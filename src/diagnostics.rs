@@ -0,0 +1,238 @@
+use std::{
+    ops::Range,
+    time::{Duration, Instant},
+};
+
+use varisat::{CnfFormula, ExtendFormula, Lit, Solver};
+
+use crate::exactly_k;
+
+///Runs build on the formula and records the range of clause indices it added under label
+///Clause indices count clauses in the order they were added to the formula
+pub fn labeled<T>(
+    formula: &mut CnfFormula,
+    labels: &mut Vec<(Range<usize>, String)>,
+    label: &str,
+    build: impl FnOnce(&mut CnfFormula) -> T,
+) -> T {
+    let start = formula.len();
+    let res = build(formula);
+    labels.push((start..formula.len(), label.to_string()));
+    res
+}
+///Returns the labels of every group containing at least one of the core clauses
+///Each label is returned once, in the order the groups were recorded
+pub fn explain_core(
+    labels: &[(Range<usize>, String)],
+    core_clause_indices: &[usize],
+) -> Vec<String> {
+    labels
+        .iter()
+        .filter(|(range, _)| core_clause_indices.iter().any(|i| range.contains(i)))
+        .map(|(_, label)| label.clone())
+        .collect()
+}
+///Returns an iterator over the clauses of the formula, in the order they were added
+pub fn clauses(formula: &CnfFormula) -> impl Iterator<Item = &[Lit]> {
+    formula.iter()
+}
+///Returns the number of clauses in the formula
+pub fn clause_count(formula: &CnfFormula) -> usize {
+    formula.len()
+}
+///Returns the edges of the implication graph of the binary clauses of the formula
+///A clause x | y gives the edges !x -> y and !y -> x, in that order. Clauses of any other
+///length are ignored.
+pub fn implication_edges(formula: &CnfFormula) -> Vec<(Lit, Lit)> {
+    formula
+        .iter()
+        .filter_map(|clause| match *clause {
+            [x, y] => Some([(!x, y), (!y, x)]),
+            _ => None,
+        })
+        .flatten()
+        .collect()
+}
+///Returns a minimal unsatisfiable subset of the clauses, or None if the formula is
+///satisfiable
+///Each clause is dropped in turn and left out for good if the rest stays unsatisfiable,
+///so removing any clause of the result makes it satisfiable. This takes one solve per
+///clause. Clauses are kept in their original order, with the same variable count.
+pub fn minimize_unsat_core(formula: &CnfFormula) -> Option<CnfFormula> {
+    let satisfiable = |clauses: &[&[Lit]]| {
+        let mut solver = Solver::new();
+        solver.add_formula(&CnfFormula::from(clauses.iter().copied()));
+        solver
+            .solve()
+            .expect("solving without proof processors cannot fail")
+    };
+    let mut core: Vec<&[Lit]> = formula.iter().collect();
+    if satisfiable(&core) {
+        return None;
+    }
+    let mut i = 0;
+    while i < core.len() {
+        let clause = core.remove(i);
+        if satisfiable(&core) {
+            core.insert(i, clause);
+            i += 1;
+        }
+    }
+    let mut minimal = CnfFormula::from(core);
+    minimal.set_var_count(formula.var_count());
+    Some(minimal)
+}
+///Returns one "name = value" line per named literal, giving its value in the model
+///A literal whose variable the model leaves out is shown as unassigned.
+pub fn format_model(named: &[(&str, Lit)], model: &[Lit]) -> String {
+    named
+        .iter()
+        .map(|&(name, lit)| {
+            let value = if model.contains(&lit) {
+                "true"
+            } else if model.contains(&!lit) {
+                "false"
+            } else {
+                "unassigned"
+            };
+            format!("{} = {}\n", name, value)
+        })
+        .collect()
+}
+///The size of a built formula and how long building it took
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuildStats {
+    ///The number of clauses in the formula
+    pub clauses: usize,
+    ///The number of variables in the formula, inputs included
+    pub variables: usize,
+    ///The time spent adding the clauses
+    pub duration: Duration,
+}
+///Returns a formula requiring exactly k of vars_count fresh inputs to be true, built with
+///exactly_k, and the statistics of building it
+pub fn build_exactly_k_profiled(vars_count: usize, k: usize) -> (CnfFormula, BuildStats) {
+    let mut formula = CnfFormula::new();
+    let vars: Vec<Lit> = formula.new_lit_iter(vars_count).collect();
+    let start = Instant::now();
+    exactly_k(&mut formula, &vars, k);
+    let duration = start.elapsed();
+    let stats = BuildStats {
+        clauses: clause_count(&formula),
+        variables: formula.var_count(),
+        duration,
+    };
+    (formula, stats)
+}
+#[cfg(test)]
+mod tests {
+    use varisat::{CnfFormula, ExtendFormula, Lit};
+
+    use crate::{
+        add_at_most_one, add_at_most_one_pairwise, add_exactly_one, build_exactly_k_profiled,
+        clause_count, clauses, count_models, estimate_clauses_at_most_one,
+        estimate_clauses_exactly_k, explain_core, format_model, implication_edges, labeled,
+        minimize_unsat_core,
+    };
+    #[test]
+    fn exactly_one_clauses() {
+        let mut formula = CnfFormula::new();
+        let (a, b) = formula.new_lits();
+        add_exactly_one(&mut formula, &[a, b]);
+        let commander = Lit::from_index(2, true);
+        let expected = vec![
+            vec![!b, !a],
+            vec![a, b, !commander],
+            vec![commander, !a],
+            vec![commander, !b],
+            vec![commander],
+        ];
+        let actual: Vec<Vec<Lit>> = clauses(&formula).map(|clause| clause.to_vec()).collect();
+        assert_eq!(actual, expected);
+    }
+    #[test]
+    fn labeled_exactly_one() {
+        let mut formula = CnfFormula::new();
+        let lits: Vec<Lit> = formula.new_lit_iter(4).collect();
+        let mut labels = Vec::new();
+        labeled(&mut formula, &mut labels, "first", |formula| {
+            formula.add_clause(&[lits[0]])
+        });
+        labeled(&mut formula, &mut labels, "one of", |formula| {
+            add_exactly_one(formula, &lits)
+        });
+        assert_eq!(labels.len(), 2);
+        assert_eq!(labels[0].0, 0..1);
+        assert_eq!(labels[1].0, 1..formula.len());
+        assert_eq!(explain_core(&labels, &[formula.len() - 1]), vec!["one of"]);
+        assert_eq!(explain_core(&labels, &[1, 0]), vec!["first", "one of"]);
+        assert!(explain_core(&labels, &[]).is_empty());
+    }
+    #[test]
+    fn minimal_core() {
+        let mut formula = CnfFormula::new();
+        let (a, b) = formula.new_lits();
+        formula.add_clause(&[a]);
+        formula.add_clause(&[!a]);
+        formula.add_clause(&[b]);
+        let core = minimize_unsat_core(&formula).unwrap();
+        let core: Vec<&[Lit]> = clauses(&core).collect();
+        assert_eq!(core, vec![&[a][..], &[!a]]);
+        let mut formula = CnfFormula::new();
+        let lits: Vec<Lit> = formula.new_lit_iter(3).collect();
+        add_exactly_one(&mut formula, &lits);
+        assert!(minimize_unsat_core(&formula).is_none());
+    }
+    #[test]
+    fn at_most_one_edges() {
+        let mut formula = CnfFormula::new();
+        let (a, b, c) = formula.new_lits();
+        //add_at_most_one adds a commander and its clauses, so the pairwise encoding is used
+        //to get exactly the binary clauses between the inputs
+        add_at_most_one_pairwise(&mut formula, &[a, b, c]);
+        formula.add_clause(&[a, b, c]);
+        formula.add_clause(&[a]);
+        let mut edges = implication_edges(&formula);
+        edges.sort();
+        let mut expected = vec![(a, !b), (b, !a), (a, !c), (c, !a), (b, !c), (c, !b)];
+        expected.sort();
+        assert_eq!(edges, expected);
+    }
+    #[test]
+    fn formatted_model() {
+        let mut formula = CnfFormula::new();
+        let (a, b, c) = formula.new_lits();
+        let model = [a, !b];
+        assert_eq!(
+            format_model(&[("x1", a), ("x2", b)], &model),
+            "x1 = true\nx2 = false\n"
+        );
+        assert_eq!(
+            format_model(&[("not x2", !b), ("x3", c)], &model),
+            "not x2 = true\nx3 = unassigned\n"
+        );
+        assert_eq!(format_model(&[], &model), "");
+    }
+    #[test]
+    fn at_most_one_clause_count() {
+        let mut formula = CnfFormula::new();
+        let lits: Vec<Lit> = formula.new_lit_iter(4).collect();
+        assert_eq!(clause_count(&formula), 0);
+        add_at_most_one(&mut formula, &lits);
+        //6 pairwise clauses and the commander's clause, then one clause per input from
+        //the commander encoding and another from add_at_most_one
+        assert_eq!(clause_count(&formula), 6 + 1 + 4 + 4);
+        assert_eq!(clause_count(&formula), estimate_clauses_at_most_one(4));
+        assert_eq!(clause_count(&formula), clauses(&formula).count());
+    }
+    #[test]
+    fn profiled_exactly_k() {
+        let (formula, stats) = build_exactly_k_profiled(12, 5);
+        assert_eq!(stats.clauses, clause_count(&formula));
+        assert_eq!(stats.variables, formula.var_count());
+        assert_eq!(stats.clauses, estimate_clauses_exactly_k(12, 5));
+        //The inputs are the first variables of the formula
+        let inputs: Vec<Lit> = (0..12).map(|i| Lit::from_index(i, true)).collect();
+        assert_eq!(count_models(&formula, &inputs), 792);
+    }
+}
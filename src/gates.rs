@@ -0,0 +1,157 @@
+use varisat::{CnfFormula, ExtendFormula, Lit};
+
+///Returns a literal that is true iff at least one of the inputs is true
+///Adds auxiliary variables: the returned literal
+pub fn or_gate(formula: &mut CnfFormula, inputs: &[Lit]) -> Lit {
+    let out = formula.new_lit();
+    for &input in inputs {
+        formula.add_clause(&[!input, out]);
+    }
+    let mut clause = inputs.to_vec();
+    clause.push(!out);
+    formula.add_clause(&clause);
+    out
+}
+///Returns a literal that is true iff all of the inputs are true
+///Adds auxiliary variables: the returned literal
+pub fn and_gate(formula: &mut CnfFormula, inputs: &[Lit]) -> Lit {
+    let out = formula.new_lit();
+    for &input in inputs {
+        formula.add_clause(&[input, !out]);
+    }
+    let mut clause: Vec<Lit> = inputs.iter().map(|&input| !input).collect();
+    clause.push(out);
+    formula.add_clause(&clause);
+    out
+}
+///Returns a literal that is true iff exactly one of a and b is true
+///Adds auxiliary variables: the returned literal
+pub fn xor_gate(formula: &mut CnfFormula, a: Lit, b: Lit) -> Lit {
+    let out = formula.new_lit();
+    formula.add_clause(&[!a, !b, !out]);
+    formula.add_clause(&[a, b, !out]);
+    formula.add_clause(&[!a, b, out]);
+    formula.add_clause(&[a, !b, out]);
+    out
+}
+///Adds clauses requiring the xor of the inputs to be the given parity
+///Adds auxiliary variables: one xor_gate per input after the first
+pub fn add_parity(formula: &mut CnfFormula, vars: &[Lit], parity: bool) {
+    match vars.split_first() {
+        Some((&first, rest)) => {
+            let xor = rest
+                .iter()
+                .fold(first, |acc, &var| xor_gate(formula, acc, var));
+            formula.add_clause(&[if parity { xor } else { !xor }]);
+        }
+        None => {
+            if parity {
+                formula.add_clause(&[]);
+            }
+        }
+    }
+}
+///Returns a literal that is true iff every variable has the value given for it
+///Adds auxiliary variables: the returned literal
+pub fn reified_is_assignment(formula: &mut CnfFormula, vars: &[Lit], assignment: &[bool]) -> Lit {
+    assert!(vars.len() == assignment.len(), "one value per variable");
+    let matches: Vec<Lit> = vars
+        .iter()
+        .zip(assignment)
+        .map(|(&var, &value)| if value { var } else { !var })
+        .collect();
+    and_gate(formula, &matches)
+}
+///Adds clauses requiring a and b to have the same value
+///Adds no auxiliary variables
+pub fn add_equal(formula: &mut CnfFormula, a: Lit, b: Lit) {
+    if a != b {
+        formula.add_clause(&[!a, b]);
+        formula.add_clause(&[a, !b]);
+    }
+}
+///Returns an iterator over n fresh literals of the formula
+///This is ExtendFormula::new_lit_iter, which already works on any formula or solver, for
+///generic code that prefers a free function. Literals are allocated as the iterator is
+///advanced.
+pub fn fresh_iter<F: ExtendFormula>(formula: &mut F, n: usize) -> impl Iterator<Item = Lit> + '_ {
+    formula.new_lit_iter(n)
+}
+///Returns a literal forced to the given value by a unit clause
+///Adds auxiliary variables: the returned literal
+pub fn const_lit(formula: &mut impl ExtendFormula, value: bool) -> Lit {
+    let lit = formula.new_lit();
+    formula.add_clause(&[if value { lit } else { !lit }]);
+    lit
+}
+#[cfg(test)]
+mod tests {
+    use varisat::{CnfFormula, ExtendFormula, Lit, Solver};
+
+    use crate::test_util::truth_table;
+    use crate::{and_gate, const_lit, fresh_iter, or_gate, reified_is_assignment, xor_gate};
+    #[test]
+    fn or_truth_table() {
+        assert_eq!(truth_table(2, or_gate), vec![false, true, true, true]);
+        assert_eq!(truth_table(0, or_gate), vec![false]);
+    }
+    #[test]
+    fn and_truth_table() {
+        assert_eq!(truth_table(2, and_gate), vec![false, false, false, true]);
+        assert_eq!(truth_table(0, and_gate), vec![true]);
+    }
+    #[test]
+    fn xor_truth_table() {
+        assert_eq!(
+            truth_table(2, |formula, lits| xor_gate(formula, lits[0], lits[1])),
+            vec![false, true, true, false]
+        );
+    }
+    #[test]
+    fn constants() {
+        assert_eq!(
+            truth_table(0, |formula, _| const_lit(formula, true)),
+            vec![true]
+        );
+        assert_eq!(
+            truth_table(0, |formula, _| const_lit(formula, false)),
+            vec![false]
+        );
+    }
+    #[test]
+    fn is_assignment() {
+        let expected: Vec<bool> = (0..8).map(|bits| bits == 0b101).collect();
+        assert_eq!(
+            truth_table(3, |formula, lits| reified_is_assignment(
+                formula,
+                lits,
+                &[true, false, true]
+            )),
+            expected
+        );
+    }
+    //Forces exactly the first of n fresh literals true
+    fn first_of(formula: &mut impl ExtendFormula, n: usize) -> Vec<Lit> {
+        let lits: Vec<Lit> = fresh_iter(formula, n).collect();
+        for (i, &lit) in lits.iter().enumerate() {
+            formula.add_clause(&[if i == 0 { lit } else { !lit }]);
+        }
+        lits
+    }
+    #[test]
+    fn generic_fresh_literals() {
+        let mut formula = CnfFormula::new();
+        formula.new_lit();
+        let lits = first_of(&mut formula, 3);
+        assert_eq!(formula.var_count(), 4);
+        assert_eq!(lits[0].index(), 1);
+        let mut solver = Solver::new();
+        solver.add_formula(&formula);
+        let more = first_of(&mut solver, 2);
+        assert!(solver.solve().unwrap());
+        let model = solver.model().unwrap();
+        assert!(model.contains(&lits[0]) && model.contains(&!lits[2]));
+        assert!(model.contains(&more[0]) && model.contains(&!more[1]));
+        assert!(more[0].index() >= 4);
+    }
+}
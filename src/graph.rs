@@ -0,0 +1,108 @@
+use varisat::{CnfFormula, ExtendFormula, Lit};
+
+use crate::{add_exactly_one, decode_one_hot};
+
+///Adds clauses requiring the vertices to be colored with the given number of colors
+///so that the endpoints of every edge get different colors
+///Returns one literal per vertex and color, true iff the vertex has that color.
+///Each edge adds one binary clause per color.
+///Adds auxiliary variables: the returned color literals, plus those of add_exactly_one per
+///vertex
+pub fn add_graph_coloring(
+    formula: &mut CnfFormula,
+    num_vertices: usize,
+    edges: &[(usize, usize)],
+    colors: usize,
+) -> Vec<Vec<Lit>> {
+    assert!(
+        edges
+            .iter()
+            .all(|&(u, v)| u < num_vertices && v < num_vertices),
+        "edge endpoint out of range"
+    );
+    let vertex_colors: Vec<Vec<Lit>> = (0..num_vertices)
+        .map(|_| formula.new_lit_iter(colors).collect())
+        .collect();
+    for vertex in &vertex_colors {
+        add_exactly_one(formula, vertex);
+    }
+    for &(u, v) in edges {
+        for (&a, &b) in vertex_colors[u].iter().zip(&vertex_colors[v]) {
+            formula.add_clause(&[!a, !b]);
+        }
+    }
+    vertex_colors
+}
+///Returns the color of each vertex in the model, given the literals from add_graph_coloring
+///Panics if a vertex does not have exactly one color, which a model of the coloring
+///constraints never allows.
+pub fn decode_coloring(vertex_vars: &[Vec<Lit>], model: &[Lit]) -> Vec<usize> {
+    vertex_vars
+        .iter()
+        .enumerate()
+        .map(|(vertex, colors)| {
+            decode_one_hot(colors, model)
+                .unwrap_or_else(|| panic!("vertex {} does not have exactly one color", vertex))
+        })
+        .collect()
+}
+#[cfg(test)]
+mod tests {
+    use varisat::{CnfFormula, Lit};
+
+    use crate::test_util::solve;
+    use crate::{add_graph_coloring, commander_aux_count, decode_coloring};
+    const TRIANGLE: [(usize, usize); 3] = [(0, 1), (1, 2), (2, 0)];
+    #[test]
+    fn triangle_two_colors() {
+        let mut formula = CnfFormula::new();
+        add_graph_coloring(&mut formula, 3, &TRIANGLE, 2);
+        assert!(solve(&formula).is_none());
+    }
+    #[test]
+    fn triangle_three_colors() {
+        let mut formula = CnfFormula::new();
+        let vertex_colors = add_graph_coloring(&mut formula, 3, &TRIANGLE, 3);
+        let model = solve(&formula).unwrap();
+        let colors = decode_coloring(&vertex_colors, &model);
+        for &(u, v) in &TRIANGLE {
+            assert_ne!(colors[u], colors[v]);
+        }
+    }
+    #[test]
+    fn decode_wheel() {
+        //A 4-cycle with a hub joined to every cycle vertex
+        let edges = [
+            (0, 1),
+            (1, 2),
+            (2, 3),
+            (3, 0),
+            (4, 0),
+            (4, 1),
+            (4, 2),
+            (4, 3),
+        ];
+        let mut formula = CnfFormula::new();
+        let vertex_colors = add_graph_coloring(&mut formula, 5, &edges, 3);
+        let model = solve(&formula).unwrap();
+        let colors = decode_coloring(&vertex_colors, &model);
+        assert_eq!(colors.len(), 5);
+        for &(u, v) in &edges {
+            assert_ne!(colors[u], colors[v]);
+        }
+    }
+    #[test]
+    #[should_panic]
+    fn decode_uncolored() {
+        let mut formula = CnfFormula::new();
+        let vertex_colors = add_graph_coloring(&mut formula, 2, &[(0, 1)], 2);
+        let model: Vec<Lit> = vertex_colors.iter().flatten().map(|&l| !l).collect();
+        decode_coloring(&vertex_colors, &model);
+    }
+    #[test]
+    fn edges_add_no_variables() {
+        let mut formula = CnfFormula::new();
+        add_graph_coloring(&mut formula, 3, &TRIANGLE, 3);
+        assert_eq!(formula.var_count(), 3 * (3 + commander_aux_count(3)));
+    }
+}
@@ -0,0 +1,291 @@
+use std::ops::Range;
+
+use varisat::{CnfFormula, ExtendFormula, Lit};
+
+use crate::{add_all_different, add_at_most_one, add_exactly_one, add_lex_less_equal, exactly_k};
+
+fn assert_square(matrix: &[&[Lit]]) {
+    for row in matrix {
+        assert!(row.len() == matrix.len(), "matrix must be square");
+    }
+}
+///Adds clauses requiring exactly one literal to be true in each row and in each column
+///of a square matrix
+///Adds auxiliary variables: those of add_exactly_one per row and column
+pub fn add_permutation_matrix(formula: &mut CnfFormula, matrix: &[&[Lit]]) {
+    assert_square(matrix);
+    for row in matrix {
+        add_exactly_one(formula, row);
+    }
+    for col in 0..matrix.len() {
+        let column: Vec<Lit> = matrix.iter().map(|row| row[col]).collect();
+        add_exactly_one(formula, &column);
+    }
+}
+///Adds clauses requiring at most one literal to be true along every diagonal
+///and every anti-diagonal of a square matrix
+///Adds auxiliary variables: those of add_at_most_one per diagonal
+pub fn add_diagonals_at_most_one(formula: &mut CnfFormula, matrix: &[&[Lit]]) {
+    assert_square(matrix);
+    let n = matrix.len();
+    //Diagonals of length 1 need no clause, those are d = 0 and d = 2n - 2
+    //Along diagonal d the column minus the row is d + 1 - n, along anti-diagonal d their sum is d
+    for d in 1..(2 * n).saturating_sub(2) {
+        let diagonal: Vec<Lit> = (0..n)
+            .filter(|&row| row + d >= n - 1 && row + d < 2 * n - 1)
+            .map(|row| matrix[row][row + d + 1 - n])
+            .collect();
+        add_at_most_one(formula, &diagonal);
+        let anti_diagonal: Vec<Lit> = (0..n)
+            .filter(|&row| row <= d && d - row < n)
+            .map(|row| matrix[row][d - row])
+            .collect();
+        add_at_most_one(formula, &anti_diagonal);
+    }
+}
+///Adds clauses requiring exactly k literals to be true in the rectangular region
+///of the grid given by the row and column ranges
+///Adds auxiliary variables: those of exactly_k over the region
+pub fn add_region_exactly_k(
+    formula: &mut CnfFormula,
+    grid: &[&[Lit]],
+    rows: Range<usize>,
+    cols: Range<usize>,
+    k: usize,
+) {
+    assert!(rows.end <= grid.len(), "row range out of bounds");
+    let mut region = Vec::with_capacity(rows.len() * cols.len());
+    for row in &grid[rows] {
+        assert!(cols.end <= row.len(), "column range out of bounds");
+        region.extend_from_slice(&row[cols.clone()]);
+    }
+    assert!(k <= region.len(), "region has fewer than k cells");
+    exactly_k(formula, &region, k);
+}
+///Adds clauses requiring an n by n grid of one-hot cells over n values to be a Latin
+///square, with every value once in each row and in each column
+///Returns the cells by row and column, each with one literal per value.
+///Adds auxiliary variables: the returned cell literals, plus those of the one-hot constraints
+pub fn add_latin_square(formula: &mut CnfFormula, n: usize) -> Vec<Vec<Vec<Lit>>> {
+    let cells: Vec<Vec<Vec<Lit>>> = (0..n)
+        .map(|_| (0..n).map(|_| formula.new_lit_iter(n).collect()).collect())
+        .collect();
+    for row in &cells {
+        for cell in row {
+            add_exactly_one(formula, cell);
+        }
+        let row: Vec<&[Lit]> = row.iter().map(|cell| &cell[..]).collect();
+        add_all_different(formula, &row);
+    }
+    for col in 0..n {
+        let column: Vec<&[Lit]> = cells.iter().map(|row| &row[col][..]).collect();
+        add_all_different(formula, &column);
+    }
+    cells
+}
+///Adds clauses ordering the rows and the columns of a matrix lexicographically, each at
+///most the next, which breaks the symmetry of permuting rows and columns
+///Adds auxiliary variables: those of add_lex_less_equal
+pub fn add_double_lex(formula: &mut CnfFormula, matrix: &[&[Lit]]) {
+    let width = matrix.first().map_or(0, |row| row.len());
+    assert!(
+        matrix.iter().all(|row| row.len() == width),
+        "matrix must be rectangular"
+    );
+    for pair in matrix.windows(2) {
+        add_lex_less_equal(formula, pair[0], pair[1]);
+    }
+    let columns: Vec<Vec<Lit>> = (0..width)
+        .map(|col| matrix.iter().map(|row| row[col]).collect())
+        .collect();
+    for pair in columns.windows(2) {
+        add_lex_less_equal(formula, &pair[0], &pair[1]);
+    }
+}
+///Adds clauses requiring every true cell of the grid to have a true cell above, below,
+///left or right of it
+///Cells on the edges only count the neighbors inside the grid, a cell with none is false.
+///Adds no auxiliary variables
+pub fn add_neighbor_support(formula: &mut CnfFormula, grid: &[&[Lit]]) {
+    let cell = |row: usize, col: usize| grid.get(row).and_then(|cells| cells.get(col)).copied();
+    for (row, cells) in grid.iter().enumerate() {
+        for (col, &lit) in cells.iter().enumerate() {
+            let mut clause = vec![!lit];
+            //Subtracting from 0 wraps to usize::MAX, which is never in bounds
+            let neighbors = [
+                (row.wrapping_sub(1), col),
+                (row + 1, col),
+                (row, col.wrapping_sub(1)),
+                (row, col + 1),
+            ];
+            clause.extend(neighbors.iter().filter_map(|&(row, col)| cell(row, col)));
+            formula.add_clause(&clause);
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use varisat::{CnfFormula, ExtendFormula, Lit};
+
+    use crate::test_util::solve;
+    use crate::{
+        add_diagonals_at_most_one, add_double_lex, add_latin_square, add_neighbor_support,
+        add_permutation_matrix, add_region_exactly_k, all_models, count_models, decode_one_hot,
+    };
+    fn new_grid(formula: &mut CnfFormula, n: usize) -> Vec<Vec<Lit>> {
+        (0..n).map(|_| formula.new_lit_iter(n).collect()).collect()
+    }
+    #[test]
+    fn permutation_3x3() {
+        let mut formula = CnfFormula::new();
+        let grid = new_grid(&mut formula, 3);
+        let rows: Vec<&[Lit]> = grid.iter().map(|row| &row[..]).collect();
+        add_permutation_matrix(&mut formula, &rows);
+        let model = solve(&formula).unwrap();
+        for i in 0..3 {
+            assert_eq!(grid[i].iter().filter(|l| model.contains(l)).count(), 1);
+            assert_eq!(grid.iter().filter(|row| model.contains(&row[i])).count(), 1);
+        }
+        formula.add_clause(&[grid[1][0]]);
+        formula.add_clause(&[grid[1][2]]);
+        assert!(solve(&formula).is_none());
+    }
+    #[test]
+    fn queens_4x4() {
+        let mut formula = CnfFormula::new();
+        let grid = new_grid(&mut formula, 4);
+        let rows: Vec<&[Lit]> = grid.iter().map(|row| &row[..]).collect();
+        add_permutation_matrix(&mut formula, &rows);
+        add_diagonals_at_most_one(&mut formula, &rows);
+        let model = solve(&formula).unwrap();
+        let queens: Vec<usize> = grid
+            .iter()
+            .map(|row| row.iter().position(|l| model.contains(l)).unwrap())
+            .collect();
+        for i in 0..4 {
+            for j in 0..i {
+                assert_ne!(queens[i], queens[j]);
+                assert_ne!(queens[i] + j, queens[j] + i);
+                assert_ne!(queens[i] + i, queens[j] + j);
+            }
+        }
+    }
+    #[test]
+    fn queens_3x3_unsat() {
+        let mut formula = CnfFormula::new();
+        let grid = new_grid(&mut formula, 3);
+        let rows: Vec<&[Lit]> = grid.iter().map(|row| &row[..]).collect();
+        add_permutation_matrix(&mut formula, &rows);
+        add_diagonals_at_most_one(&mut formula, &rows);
+        assert!(solve(&formula).is_none());
+    }
+    #[test]
+    fn region_2x2() {
+        let mut formula = CnfFormula::new();
+        let grid = new_grid(&mut formula, 4);
+        let rows: Vec<&[Lit]> = grid.iter().map(|row| &row[..]).collect();
+        add_region_exactly_k(&mut formula, &rows, 0..2, 0..2, 2);
+        let model = solve(&formula).unwrap();
+        let count = grid[..2]
+            .iter()
+            .flat_map(|row| &row[..2])
+            .filter(|l| model.contains(l))
+            .count();
+        assert_eq!(count, 2);
+        formula.add_clause(&[grid[2][2]]);
+        formula.add_clause(&[grid[0][1]]);
+        formula.add_clause(&[grid[1][0]]);
+        assert!(solve(&formula).is_some());
+        formula.add_clause(&[grid[1][1]]);
+        assert!(solve(&formula).is_none());
+    }
+    #[test]
+    #[should_panic]
+    fn region_out_of_bounds() {
+        let mut formula = CnfFormula::new();
+        let grid = new_grid(&mut formula, 4);
+        let rows: Vec<&[Lit]> = grid.iter().map(|row| &row[..]).collect();
+        add_region_exactly_k(&mut formula, &rows, 2..4, 3..5, 1);
+    }
+    #[test]
+    #[should_panic]
+    fn permutation_not_square() {
+        let mut formula = CnfFormula::new();
+        let grid = new_grid(&mut formula, 3);
+        let rows: Vec<&[Lit]> = grid[..2].iter().map(|row| &row[..]).collect();
+        add_permutation_matrix(&mut formula, &rows);
+    }
+    fn decode_square(cells: &[Vec<Vec<Lit>>], model: &[Lit]) -> Vec<Vec<usize>> {
+        cells
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|cell| decode_one_hot(cell, model).unwrap())
+                    .collect()
+            })
+            .collect()
+    }
+    #[test]
+    fn latin_3x3() {
+        let mut formula = CnfFormula::new();
+        let cells = add_latin_square(&mut formula, 3);
+        let square = decode_square(&cells, &solve(&formula).unwrap());
+        for i in 0..3 {
+            let mut row = square[i].clone();
+            let mut column: Vec<usize> = square.iter().map(|row| row[i]).collect();
+            row.sort();
+            column.sort();
+            assert_eq!(row, vec![0, 1, 2]);
+            assert_eq!(column, vec![0, 1, 2]);
+        }
+        formula.add_clause(&[cells[0][0][2]]);
+        formula.add_clause(&[cells[1][1][2]]);
+        assert!(solve(&formula).is_some());
+        formula.add_clause(&[cells[2][2][0]]);
+        assert!(solve(&formula).is_none());
+    }
+    #[test]
+    fn double_lex_3x3() {
+        let mut formula = CnfFormula::new();
+        let grid = new_grid(&mut formula, 3);
+        let cells: Vec<Lit> = grid.iter().flatten().copied().collect();
+        assert_eq!(count_models(&formula, &cells), 512);
+        let rows: Vec<&[Lit]> = grid.iter().map(|row| &row[..]).collect();
+        add_double_lex(&mut formula, &rows);
+        let count = count_models(&formula, &cells);
+        assert!(count > 0 && count < 512);
+        let models = all_models(&formula, &cells, None);
+        for model in models {
+            for i in 0..2 {
+                assert!(model[3 * i..3 * i + 3] <= model[3 * i + 3..3 * i + 6]);
+                let column =
+                    |col: usize| -> Vec<bool> { (0..3).map(|row| model[3 * row + col]).collect() };
+                assert!(column(i) <= column(i + 1));
+            }
+        }
+    }
+    #[test]
+    fn neighbor_support_3x3() {
+        let mut formula = CnfFormula::new();
+        let grid = new_grid(&mut formula, 3);
+        let rows: Vec<&[Lit]> = grid.iter().map(|row| &row[..]).collect();
+        add_neighbor_support(&mut formula, &rows);
+        formula.add_clause(&[grid[1][1]]);
+        let mut supported = CnfFormula::from(formula.iter());
+        supported.add_clause(&[grid[0][1]]);
+        assert!(solve(&supported).is_some());
+        for (row, col) in [(0, 1), (2, 1), (1, 0), (1, 2)] {
+            formula.add_clause(&[!grid[row][col]]);
+        }
+        assert!(solve(&formula).is_none());
+        //A corner only has two neighbors
+        let mut formula = CnfFormula::new();
+        let grid = new_grid(&mut formula, 3);
+        let rows: Vec<&[Lit]> = grid.iter().map(|row| &row[..]).collect();
+        add_neighbor_support(&mut formula, &rows);
+        formula.add_clause(&[grid[0][0]]);
+        formula.add_clause(&[!grid[0][1]]);
+        formula.add_clause(&[!grid[1][0]]);
+        assert!(solve(&formula).is_none());
+    }
+}
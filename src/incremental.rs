@@ -0,0 +1,66 @@
+use varisat::CnfFormula;
+
+///A formula whose most recently added clauses can be removed again
+///Clauses are added through formula_mut, checkpoint records the current clause count
+///and rollback truncates back to it.
+#[derive(Default)]
+pub struct IncrementalFormula {
+    formula: CnfFormula,
+}
+impl IncrementalFormula {
+    ///Creates an empty formula
+    pub fn new() -> IncrementalFormula {
+        IncrementalFormula::default()
+    }
+    ///Returns the formula for solving
+    pub fn formula(&self) -> &CnfFormula {
+        &self.formula
+    }
+    ///Returns the formula for adding clauses and variables
+    pub fn formula_mut(&mut self) -> &mut CnfFormula {
+        &mut self.formula
+    }
+    ///Returns a checkpoint to roll back to, the current number of clauses
+    pub fn checkpoint(&self) -> usize {
+        self.formula.len()
+    }
+    ///Removes the clauses added after the checkpoint
+    ///The formula is rebuilt from the retained clauses. Variables allocated since the
+    ///checkpoint stay allocated, so literals handed out earlier never get reused.
+    pub fn rollback(&mut self, cp: usize) {
+        assert!(cp <= self.formula.len(), "checkpoint is past the end");
+        let mut retained = CnfFormula::from(self.formula.iter().take(cp));
+        retained.set_var_count(self.formula.var_count());
+        self.formula = retained;
+    }
+}
+#[cfg(test)]
+mod tests {
+    use varisat::{CnfFormula, ExtendFormula, Lit, Solver};
+
+    use crate::{add_exactly_one, IncrementalFormula};
+    fn solve(formula: &CnfFormula) -> bool {
+        let mut solver = Solver::new();
+        solver.add_formula(formula);
+        solver.solve().unwrap()
+    }
+    #[test]
+    fn rollback_conflict() {
+        let mut incremental = IncrementalFormula::new();
+        let lits: Vec<Lit> = incremental.formula_mut().new_lit_iter(5).collect();
+        add_exactly_one(incremental.formula_mut(), &lits);
+        let cp = incremental.checkpoint();
+        let var_count = incremental.formula().var_count();
+        incremental.formula_mut().add_clause(&[lits[1]]);
+        incremental.formula_mut().add_clause(&[lits[3]]);
+        assert!(!solve(incremental.formula()));
+        incremental.rollback(cp);
+        assert_eq!(incremental.checkpoint(), cp);
+        assert_eq!(incremental.formula().var_count(), var_count);
+        assert!(solve(incremental.formula()));
+        incremental.formula_mut().add_clause(&[lits[3]]);
+        assert!(solve(incremental.formula()));
+        incremental.rollback(0);
+        assert!(incremental.formula().is_empty());
+    }
+}
@@ -1,21 +1,129 @@
+//!Auxiliary variables: some encodings introduce fresh variables in addition to
+//!the inputs. Functions that do are marked "Adds auxiliary variables", functions
+//!that never do are marked "Adds no auxiliary variables". Only for the latter is a
+//!model count over all variables guaranteed to equal the count over the inputs.
+mod bitvec;
+mod cardinality;
+mod diagnostics;
+mod gates;
+mod graph;
+mod grid;
+mod incremental;
+mod onehot;
+mod pb;
+mod pool;
+mod preprocess;
+mod sequence;
+mod solve;
+#[cfg(test)]
+mod test_util;
+#[cfg(feature = "testgen")]
+mod testgen;
+
+pub use bitvec::{
+    abs_difference, add_at_most_one_transition, add_conditional_equal, add_equals_int,
+    add_gray_adjacent, add_lex_less_equal, add_strictly_increasing, bitvec_max, bitvec_min,
+    const_bits, decode_binary, less_than, modular_add, ripple_carry_add_cin, select_value,
+};
+pub use cardinality::{
+    add_at_least_k, add_at_least_k_seqcounter, add_at_most_k, add_at_most_k_seqcounter,
+    add_at_most_or_at_least, add_count_parity, add_k_nonempty_groups, exactly_k_partitioned,
+    exactly_k_with, majority_gate, threshold_gate, AtMostK, EkEncoding, SharedCounter,
+};
+pub use diagnostics::{
+    build_exactly_k_profiled, clause_count, clauses, explain_core, format_model, implication_edges,
+    labeled, minimize_unsat_core, BuildStats,
+};
+pub use gates::{
+    add_equal, add_parity, and_gate, const_lit, fresh_iter, or_gate, reified_is_assignment,
+    xor_gate,
+};
+pub use graph::{add_graph_coloring, decode_coloring};
+pub use grid::{
+    add_diagonals_at_most_one, add_double_lex, add_latin_square, add_neighbor_support,
+    add_permutation_matrix, add_region_exactly_k,
+};
+pub use incremental::IncrementalFormula;
+pub use onehot::{
+    add_all_different, add_consecutive_distinct, add_distinct_values_exactly_k,
+    add_forbidden_tuples, add_table_constraint, add_value_in_range, decode_one_hot,
+};
+pub use pb::{
+    add_bin_packing, add_knapsack, add_set_cover, add_weighted_at_least, add_weighted_at_most,
+};
+pub use pool::{with_user_vars, VarPool};
+pub use preprocess::{
+    add_transitive_implications, equivalent_literals, preprocess, propagate_units, pure_literals,
+    quick_unsat_check, remove_subsumed, simplify,
+};
+pub use sequence::{
+    add_at_most_runs, add_dfa_accepts, add_no_overlap, add_reversed, add_run_length_bounds,
+    add_run_sequence, reified_monotone, Dfa,
+};
 use smallvec::SmallVec;
+pub use solve::{
+    add_blocking_clause, all_models, count_models, hamming_distance, has_unique_solution,
+    is_model_valid, project_model, solution_multiplicity, solve_weighted_maxsat, solve_with_budget,
+    solve_with_hints, solve_with_proof, ConstraintSolver, Multiplicity, Session, SolveOutcome,
+};
+use std::{collections::HashMap, hash::Hash, ops::Range};
+#[cfg(feature = "testgen")]
+pub use testgen::random_exactly_one_instance;
 use varisat::{CnfFormula, ExtendFormula, Lit};
 ///Returns a literal that is true if exactly one of the input variables is true
 ///This uses an efficient encoding from
 ///https://www.cs.cmu.edu/~wklieber/papers/2007_efficient-cnf-encoding-for-selecting-1.pdf
+///The added clauses always require at most one input to be true, whatever the value of
+///the commander. A true commander requires one input to be true, a false commander
+///requires all inputs to be false. So in every model the commander is true iff exactly
+///one input is true and false iff none is, while assignments with more than one true
+///input are ruled out entirely rather than making the commander false. No extra clauses
+///can change that, use reified_exactly_one for a literal that leaves the inputs free.
+///Adds auxiliary variables: one commander per group
 pub fn commander_exactly_one(formula: &mut CnfFormula, input_variables: &[Lit]) -> Lit {
+    commander_tree(formula, input_variables).commander
+}
+///A group of the commander encoding, as built by commander_tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommanderNode {
+    ///The commander literal of the group
+    pub commander: Lit,
+    ///The members of the group, in input order
+    pub children: Vec<CommanderChild>,
+}
+///A member of a commander group, either a subgroup or one of the inputs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommanderChild {
+    ///The index of an input variable
+    Input(usize),
+    ///A subgroup with its own commander
+    Node(CommanderNode),
+}
+///Adds the same clauses as commander_exactly_one and returns the tree of groups it built
+///Groups of fewer than 6 members are leaves, larger ones are split into 3 subgroups
+pub fn commander_tree(formula: &mut CnfFormula, input_variables: &[Lit]) -> CommanderNode {
+    build_commander_tree(formula, input_variables, 0)
+}
+fn build_commander_tree(
+    formula: &mut CnfFormula,
+    input_variables: &[Lit],
+    offset: usize,
+) -> CommanderNode {
     let numvar = input_variables.len();
-    let variables;
     let mut vars = SmallVec::<[Lit; 5]>::new();
-    if numvar < 6 {
-        variables = input_variables;
+    let (variables, children) = if numvar < 6 {
+        let children = (0..numvar).map(|i| CommanderChild::Input(offset + i));
+        (input_variables, children.collect())
     } else {
         let chunk_size = num::integer::div_ceil(numvar, 3);
-        for chunk in input_variables.chunks(chunk_size) {
-            vars.push(commander_exactly_one(formula, chunk));
+        let mut children = Vec::new();
+        for (i, chunk) in input_variables.chunks(chunk_size).enumerate() {
+            let node = build_commander_tree(formula, chunk, offset + i * chunk_size);
+            vars.push(node.commander);
+            children.push(CommanderChild::Node(node));
         }
-        variables = &vars;
-    }
+        (&vars[..], children)
+    };
     let commander = formula.new_lit();
     for i in 0..variables.len() {
         //No more than one var can be true
@@ -30,39 +138,350 @@ pub fn commander_exactly_one(formula: &mut CnfFormula, input_variables: &[Lit])
         //false commander implies all false inputs
         formula.add_clause(&[commander, !var]);
     }
+    CommanderNode {
+        commander,
+        children,
+    }
+}
+///Adds clauses with the same meaning as commander_exactly_one, choosing the encoding of
+///each group to need the fewest auxiliary variables
+///Groups of fewer than 6 members are leaves with pairwise at-most-one clauses, as in
+///commander_exactly_one. Larger groups use whichever of the bitwise at-most-one encoding or
+///a split into 2 to 5 subgroups of near equal size needs fewer auxiliary variables.
+///Adds auxiliary variables: one commander per group, plus the bits of bitwise groups
+pub fn commander_exactly_one_min_vars(formula: &mut CnfFormula, vars: &[Lit]) -> Lit {
+    let plans = min_vars_plans(vars.len());
+    build_min_vars(formula, vars, &plans)
+}
+#[derive(Clone, Copy)]
+enum GroupBase {
+    Pairwise,
+    Bitwise,
+    Split(usize),
+}
+//Splits n members into parts near equal sizes, the larger ones first
+fn split_sizes(n: usize, parts: usize) -> impl Iterator<Item = usize> {
+    (0..parts).map(move |i| n / parts + usize::from(i < n % parts))
+}
+//Returns for each size up to n the auxiliary variable count and base of its cheapest group
+fn min_vars_plans(n: usize) -> Vec<(usize, GroupBase)> {
+    let mut plans = Vec::with_capacity(n + 1);
+    for m in 0..=n {
+        if m < 6 {
+            plans.push((1, GroupBase::Pairwise));
+            continue;
+        }
+        let width = (usize::BITS - (m - 1).leading_zeros()) as usize;
+        let mut best = (1 + width, GroupBase::Bitwise);
+        for parts in 2..=5 {
+            let vars = 1 + split_sizes(m, parts)
+                .map(|size| plans[size].0)
+                .sum::<usize>();
+            if vars < best.0 {
+                best = (vars, GroupBase::Split(parts));
+            }
+        }
+        plans.push(best);
+    }
+    plans
+}
+fn build_min_vars(formula: &mut CnfFormula, vars: &[Lit], plans: &[(usize, GroupBase)]) -> Lit {
+    let mut members = Vec::new();
+    let base = plans[vars.len()].1;
+    match base {
+        GroupBase::Pairwise | GroupBase::Bitwise => members.extend_from_slice(vars),
+        GroupBase::Split(parts) => {
+            let mut start = 0;
+            for size in split_sizes(vars.len(), parts) {
+                members.push(build_min_vars(formula, &vars[start..start + size], plans));
+                start += size;
+            }
+        }
+    }
+    let commander = formula.new_lit();
+    match base {
+        GroupBase::Bitwise => add_at_most_one_bitwise(formula, &members),
+        GroupBase::Pairwise | GroupBase::Split(_) => add_at_most_one_pairwise(formula, &members),
+    }
+    let mut with_command = members.clone();
+    with_command.push(!commander);
+    formula.add_clause(&with_command);
+    for &member in &members {
+        formula.add_clause(&[commander, !member]);
+    }
     commander
 }
+///Returns a literal that is true iff exactly one of the input variables is true
+///Unlike commander_exactly_one the inputs are left unconstrained, so the literal can be
+///used as a flag in both directions. The inputs are grouped as in the commander encoding,
+///each group reifying whether at least one and whether at least two of its inputs are true.
+///Adds auxiliary variables
+pub fn commander_exactly_one_reified(formula: &mut CnfFormula, vars: &[Lit]) -> Lit {
+    let (any, two) = reified_group_counts(formula, vars);
+    match two {
+        Some(two) => and_gate(formula, &[any, !two]),
+        None => any,
+    }
+}
+//Returns literals true iff at least one and iff at least two of the inputs are true,
+//the second is None when there are fewer than two inputs
+fn reified_group_counts(formula: &mut CnfFormula, vars: &[Lit]) -> (Lit, Option<Lit>) {
+    if vars.len() == 1 {
+        return (vars[0], None);
+    }
+    let members: Vec<(Lit, Option<Lit>)> = if vars.len() < 6 {
+        vars.iter().map(|&var| (var, None)).collect()
+    } else {
+        let chunk_size = num::integer::div_ceil(vars.len(), 3);
+        vars.chunks(chunk_size)
+            .map(|chunk| reified_group_counts(formula, chunk))
+            .collect()
+    };
+    let anys: Vec<Lit> = members.iter().map(|&(any, _)| any).collect();
+    let any = or_gate(formula, &anys);
+    if members.len() < 2 {
+        return (any, None);
+    }
+    //Two members with one each, or one member with two
+    let mut twos: Vec<Lit> = members.iter().filter_map(|&(_, two)| two).collect();
+    for i in 0..anys.len() {
+        for j in 0..i {
+            twos.push(and_gate(formula, &[anys[i], anys[j]]));
+        }
+    }
+    (any, Some(or_gate(formula, &twos)))
+}
+///Adds clauses requiring exactly one input variable to be true and returns the commander
+///of the whole group, with the input range and commander of every subgroup below it
+///A subgroup commander is true iff one of the inputs in its range is, so other
+///constraints can refer to the choice falling within a subgroup.
+///Adds auxiliary variables
+pub fn exactly_one_tree(formula: &mut CnfFormula, vars: &[Lit]) -> (Lit, Vec<(Range<usize>, Lit)>) {
+    let tree = commander_tree(formula, vars);
+    formula.add_clause(&[tree.commander]);
+    let mut groups = Vec::new();
+    subgroup_ranges(&tree, &mut groups);
+    (tree.commander, groups)
+}
+//Returns the range of inputs under the node, recording those of the subgroups below it
+fn subgroup_ranges(node: &CommanderNode, groups: &mut Vec<(Range<usize>, Lit)>) -> Range<usize> {
+    let mut range: Option<Range<usize>> = None;
+    for child in &node.children {
+        let child_range = match child {
+            CommanderChild::Input(i) => *i..*i + 1,
+            CommanderChild::Node(subgroup) => {
+                let child_range = subgroup_ranges(subgroup, groups);
+                groups.push((child_range.clone(), subgroup.commander));
+                child_range
+            }
+        };
+        range = Some(match range {
+            Some(range) => range.start..child_range.end,
+            None => child_range,
+        });
+    }
+    range.unwrap_or(0..0)
+}
 ///Adds a clause requiring exactly one input variable to be true
 ///This uses the same efficient encoding as the commander
+///Adds auxiliary variables
 pub fn add_exactly_one(formula: &mut CnfFormula, input_variables: &[Lit]) {
     let commander = commander_exactly_one(formula, input_variables);
     formula.add_clause(&[commander]);
 }
 ///Adds a clause requiring at most one input variable to be true
 ///This uses the same efficient encoding as the commander
+///Unit propagation alone forces the other inputs false once one is true: the input forces
+///the commanders of its groups true, each of which excludes its sibling commanders, and a
+///false commander forces its members false. So no clauses beyond the encoding are needed.
+///Adds auxiliary variables
 pub fn add_at_most_one(formula: &mut CnfFormula, input_variables: &[Lit]) {
     let commander = commander_exactly_one(formula, input_variables);
     for &var in input_variables {
         formula.add_clause(&[commander, !var]);
     }
 }
+///Adds clauses requiring at most one literal to be true among the items of each key
+///Groups are encoded in the order their keys first appear, keeping the formula deterministic.
+///Adds auxiliary variables: those of add_at_most_one per group
+pub fn add_at_most_one_by_key<K: Eq + Hash>(formula: &mut CnfFormula, items: &[(K, Lit)]) {
+    let mut group_of: HashMap<&K, usize> = HashMap::new();
+    let mut groups: Vec<Vec<Lit>> = Vec::new();
+    for (key, lit) in items {
+        let group = *group_of.entry(key).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[group].push(*lit);
+    }
+    for group in &groups {
+        add_at_most_one(formula, group);
+    }
+}
+///Adds clauses requiring at most one input variable to be true and returns a literal
+///that is true iff one of them is
+///The flag is the commander of the encoding, so this costs no more than add_at_most_one.
+///Adds auxiliary variables
+pub fn at_most_one_with_flag(formula: &mut CnfFormula, vars: &[Lit]) -> Lit {
+    commander_exactly_one(formula, vars)
+}
+///Adds clauses requiring exactly one of if_true to be true and all of if_false to be false
+///when cond is true, and the reverse when cond is false
+///Each group's commander is true iff exactly one of its inputs is and false iff none is,
+///so they are set equal to cond and its negation.
+///Adds auxiliary variables: those of commander_exactly_one for each group
+pub fn add_conditional_exactly_one(
+    formula: &mut CnfFormula,
+    cond: Lit,
+    if_true: &[Lit],
+    if_false: &[Lit],
+) {
+    let true_commander = commander_exactly_one(formula, if_true);
+    add_equal(formula, true_commander, cond);
+    let false_commander = commander_exactly_one(formula, if_false);
+    add_equal(formula, false_commander, !cond);
+}
+///Returns a literal that is true iff exactly one of the input variables is true
+///Unlike commander_exactly_one the inputs are left unconstrained.
+///Adds auxiliary variables: 3 per input plus 1
+pub fn reified_exactly_one(formula: &mut CnfFormula, vars: &[Lit]) -> Lit {
+    //Scanning the inputs in order, track whether one and whether two were seen
+    let mut any = or_gate(formula, &[]);
+    let mut two: Option<Lit> = None;
+    for &var in vars {
+        let second = and_gate(formula, &[any, var]);
+        two = Some(match two {
+            Some(two) => or_gate(formula, &[two, second]),
+            None => second,
+        });
+        any = or_gate(formula, &[any, var]);
+    }
+    match two {
+        Some(two) => and_gate(formula, &[any, !two]),
+        None => any,
+    }
+}
+///Adds a clause requiring at least one input variable to be true
+///Adds no auxiliary variables
+pub fn add_at_least_one(formula: &mut CnfFormula, vars: &[Lit]) {
+    formula.add_clause(vars);
+}
+///Adds clauses requiring at most one input variable to be true
+///This uses the pairwise encoding, one binary clause per pair of inputs
+///Adds no auxiliary variables, so the models of the formula are unchanged
+///when projected onto the inputs. The clause count is quadratic in the input length.
+pub fn add_at_most_one_pairwise(formula: &mut CnfFormula, input_variables: &[Lit]) {
+    for i in 0..input_variables.len() {
+        for j in 0..i {
+            formula.add_clause(&[!input_variables[i], !input_variables[j]]);
+        }
+    }
+}
+///Adds clauses requiring at most one input variable to be true
+///This uses the product encoding from
+///https://www.cs.upc.edu/~oliveras/espai/papers/product.pdf
+///The inputs are laid out in a near-square grid, the last row may be partial.
+///Each input implies its row and column selector, and at most one row and one column
+///selector may be true, which is encoded recursively.
+///Adds auxiliary variables: about 2 sqrt(n)
+pub fn add_at_most_one_product(formula: &mut CnfFormula, vars: &[Lit]) {
+    let n = vars.len();
+    if n <= 4 {
+        add_at_most_one_pairwise(formula, vars);
+        return;
+    }
+    let cols = (1..).find(|c| c * c >= n).unwrap();
+    let rows = num::integer::div_ceil(n, cols);
+    let row_lits: Vec<Lit> = formula.new_lit_iter(rows).collect();
+    let col_lits: Vec<Lit> = formula.new_lit_iter(cols).collect();
+    for (i, &var) in vars.iter().enumerate() {
+        formula.add_clause(&[!var, row_lits[i / cols]]);
+        formula.add_clause(&[!var, col_lits[i % cols]]);
+    }
+    add_at_most_one_product(formula, &row_lits);
+    add_at_most_one_product(formula, &col_lits);
+}
+///Adds clauses requiring at most one input variable to be true
+///This uses the bitwise (binary) encoding: each input forces a vector of
+///ceil(log2 n) auxiliary bits to its own index, so no two inputs can be true at once.
+///Adds auxiliary variables: ceil(log2 n)
+pub fn add_at_most_one_bitwise(formula: &mut CnfFormula, vars: &[Lit]) {
+    let n = vars.len();
+    if n <= 1 {
+        return;
+    }
+    let width = (usize::BITS - (n - 1).leading_zeros()) as usize;
+    let bits: Vec<Lit> = formula.new_lit_iter(width).collect();
+    for (i, &var) in vars.iter().enumerate() {
+        for (b, &bit) in bits.iter().enumerate() {
+            formula.add_clause(&[!var, if i >> b & 1 == 1 { bit } else { !bit }]);
+        }
+    }
+}
+///The at-most-one encodings of this crate, to choose one per call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AmoEncoding {
+    ///add_at_most_one, the commander encoding
+    Commander,
+    ///add_at_most_one_pairwise, without auxiliary variables
+    Pairwise,
+    ///add_at_most_one_bitwise
+    Bitwise,
+    ///add_at_most_one_product
+    Product,
+    ///add_at_most_k_seqcounter with k = 1
+    SequentialCounter,
+}
+///Adds clauses requiring at most one input variable to be true using the given encoding
+///Adds auxiliary variables: those of the chosen encoding, none for Pairwise
+pub fn add_at_most_one_with(formula: &mut CnfFormula, vars: &[Lit], enc: AmoEncoding) {
+    match enc {
+        AmoEncoding::Commander => add_at_most_one(formula, vars),
+        AmoEncoding::Pairwise => add_at_most_one_pairwise(formula, vars),
+        AmoEncoding::Bitwise => add_at_most_one_bitwise(formula, vars),
+        AmoEncoding::Product => add_at_most_one_product(formula, vars),
+        AmoEncoding::SequentialCounter => add_at_most_k_seqcounter(formula, vars, 1),
+    }
+}
+///Adds clauses requiring exactly one of the pairs to have both literals true
+///Adds auxiliary variables: one and_gate per pair, plus those of add_exactly_one
+pub fn add_exactly_one_pair(formula: &mut CnfFormula, pairs: &[(Lit, Lit)]) {
+    let both: Vec<Lit> = pairs
+        .iter()
+        .map(|&(a, b)| and_gate(formula, &[a, b]))
+        .collect();
+    add_exactly_one(formula, &both);
+}
+///Adds clauses requiring at least one literal of b to be true if any literal of a is true
+///If b is empty no literal of a can be true
+///Adds no auxiliary variables
+pub fn add_group_implies_group(formula: &mut CnfFormula, a: &[Lit], b: &[Lit]) {
+    let mut clause = Vec::with_capacity(b.len() + 1);
+    for &lit in a {
+        clause.clear();
+        clause.push(!lit);
+        clause.extend_from_slice(b);
+        formula.add_clause(&clause);
+    }
+}
 
+//Returns the minimum and maximum of the inputs, their and and their or
+//Each output is defined with clauses that propagate from a single assigned input,
+//which makes the networks built from these comparators arc consistent.
 fn sort_swap(formula: &mut CnfFormula, in1: Lit, in2: Lit) -> (Lit, Lit) {
     let (out1, out2) = formula.new_lits();
-    formula.add_clause(&[in1, in2, !out1]);
-    formula.add_clause(&[in1, in2, !out2]);
-    formula.add_clause(&[in1, !in2, !out1]);
-    formula.add_clause(&[in1, !in2, out2]);
-    formula.add_clause(&[!in1, in2, !out1]);
-    formula.add_clause(&[!in1, in2, out2]);
+    formula.add_clause(&[!out1, in1]);
+    formula.add_clause(&[!out1, in2]);
     formula.add_clause(&[!in1, !in2, out1]);
-    formula.add_clause(&[!in1, !in2, out2]);
+    formula.add_clause(&[!in1, out2]);
+    formula.add_clause(&[!in2, out2]);
+    formula.add_clause(&[in1, in2, !out2]);
     (out1, out2)
 }
 
-fn make_sorting_network(formula: &mut CnfFormula, vars: &[Lit]) -> Vec<Lit> {
-    let n = vars.len();
-    let swaps = match n {
+//Optimal comparator lists for small networks, larger ones are built recursively
+fn base_swaps(n: usize) -> Option<Vec<(usize, usize)>> {
+    match n {
         0 => Some(vec![]),
         1 => Some(vec![]),
         2 => Some(vec![(0, 1)]),
@@ -112,65 +531,86 @@ fn make_sorting_network(formula: &mut CnfFormula, vars: &[Lit]) -> Vec<Lit> {
             (5, 6),
         ]),
         _ => None,
-    };
-    let mut vars: Vec<Lit> = vars.iter().cloned().collect();
-    match swaps {
-        Some(swaps) => {
-            for (l, r) in swaps {
-                let (l_new, r_new) = sort_swap(formula, vars[l], vars[r]);
-                vars[l] = l_new;
-                vars[r] = r_new;
-            }
-            vars
-        }
-        None => {
-            let padding_amount = vars.len() % 4;
-            for _ in 0..padding_amount {
-                let lit = formula.new_lit();
-                vars.push(lit);
-                formula.add_clause(&[lit]);
-            }
-            {
-                //Sort left and right
-                let mut right = vars.split_off(vars.len() / 2);
-                let mut left = vars;
-                right = make_sorting_network(formula, &right);
-                left = make_sorting_network(formula, &left);
-                left.append(&mut right);
-                vars = left;
-            }
-            {
-                let mut odds: Vec<Lit> = vars
-                    .iter()
-                    .enumerate()
-                    .filter_map(|(i, var)| if i % 2 == 1 { Some(*var) } else { None })
-                    .collect();
-                let mut evens: Vec<Lit> = vars
-                    .iter()
-                    .enumerate()
-                    .filter_map(|(i, var)| if i % 2 == 0 { Some(*var) } else { None })
-                    .collect();
-                odds = make_sorting_network(formula, &odds);
-                evens = make_sorting_network(formula, &evens);
-                assert!(odds.len() == evens.len());
-                vars = Vec::new();
-                for i in 0..odds.len() {
-                    vars.push(evens[i]);
-                    vars.push(odds[i]);
-                }
-            }
-            for i in 1..vars.len() - 1 {
-                if i % 2 == 1 {
-                    let (l_new, r_new) = sort_swap(formula, vars[i], vars[i + 1]);
-                    vars[i] = l_new;
-                    vars[i + 1] = r_new;
-                }
-            }
-            vars.truncate(vars.len() - padding_amount);
-            vars
-        }
     }
 }
+
+fn make_sorting_network(formula: &mut CnfFormula, vars: &[Lit]) -> Vec<Lit> {
+    apply_comparators(formula, vars, &sorting_comparators(vars.len()))
+}
+///Returns the comparators of the sorting network for n inputs, as pairs of wire indices
+///Each comparator puts the minimum on its first wire and the maximum on its second, so
+///the sorted outputs are on wires 0 to n - 1 in ascending order. Larger networks are built
+///recursively and padded with wires from n up, which are inputs fixed to true.
+pub fn sorting_comparators(n: usize) -> Vec<(usize, usize)> {
+    let wires: Vec<usize> = (0..n).collect();
+    let mut next_padding = n;
+    let mut comparators = Vec::new();
+    network_comparators(&wires, &mut next_padding, &mut comparators);
+    comparators
+}
+//Appends the comparators sorting the given wires in place
+fn network_comparators(
+    wires: &[usize],
+    next_padding: &mut usize,
+    comparators: &mut Vec<(usize, usize)>,
+) {
+    if let Some(swaps) = base_swaps(wires.len()) {
+        comparators.extend(swaps.iter().map(|&(l, r)| (wires[l], wires[r])));
+        return;
+    }
+    //Pad up to a multiple of 4 so both halves and their even/odd parts line up
+    let padding_amount = (4 - wires.len() % 4) % 4;
+    let mut wires = wires.to_vec();
+    for _ in 0..padding_amount {
+        wires.push(*next_padding);
+        *next_padding += 1;
+    }
+    //Sort right and left, then the odd and even positions
+    let (left, right) = wires.split_at(wires.len() / 2);
+    network_comparators(right, next_padding, comparators);
+    network_comparators(left, next_padding, comparators);
+    let odds: Vec<usize> = wires.iter().skip(1).step_by(2).copied().collect();
+    let evens: Vec<usize> = wires.iter().step_by(2).copied().collect();
+    network_comparators(&odds, next_padding, comparators);
+    network_comparators(&evens, next_padding, comparators);
+    for i in (1..wires.len() - 1).step_by(2) {
+        comparators.push((wires[i], wires[i + 1]));
+    }
+}
+///Returns the outputs of the comparator network on the inputs, one per input
+///The network has a wire per input and beyond that up to its largest wire index, the
+///extra wires are padding inputs fixed to true. Each comparator puts the minimum of its
+///wires on the first and the maximum on the second.
+///Adds auxiliary variables: one per padding wire and two per comparator
+pub fn apply_comparators(
+    formula: &mut CnfFormula,
+    vars: &[Lit],
+    comparators: &[(usize, usize)],
+) -> Vec<Lit> {
+    let width = comparators
+        .iter()
+        .map(|&(l, r)| l.max(r) + 1)
+        .max()
+        .unwrap_or(0)
+        .max(vars.len());
+    let mut wires = vars.to_vec();
+    while wires.len() < width {
+        let lit = formula.new_lit();
+        formula.add_clause(&[lit]);
+        wires.push(lit);
+    }
+    for &(l, r) in comparators {
+        let (l_new, r_new) = sort_swap(formula, wires[l], wires[r]);
+        wires[l] = l_new;
+        wires[r] = r_new;
+    }
+    wires.truncate(vars.len());
+    wires
+}
+///Adds clauses requiring exactly k of the input variables to be true
+///The encoding is arc consistent: once k inputs are true unit propagation sets the
+///others false, and once n - k inputs are false it sets the others true.
+///Adds auxiliary variables unless k is 0 or the input length
 pub fn exactly_k(formula: &mut CnfFormula, vars: &[Lit], k: usize) {
     if k == 0 {
         for &var in vars {
@@ -188,11 +628,105 @@ pub fn exactly_k(formula: &mut CnfFormula, vars: &[Lit], k: usize) {
         formula.add_clause(&[sorted[vars.len() - k]]);
     }
 }
+///Adds clauses requiring exactly k of the input variables to be true and
+///no two variables of a forbidden pair to both be true.
+///Pairs index into vars.
+///Adds auxiliary variables: those of exactly_k
+pub fn exactly_k_with_exclusions(
+    formula: &mut CnfFormula,
+    vars: &[Lit],
+    k: usize,
+    forbidden_pairs: &[(usize, usize)],
+) {
+    exactly_k(formula, vars, k);
+    for &(i, j) in forbidden_pairs {
+        formula.add_clause(&[!vars[i], !vars[j]]);
+    }
+}
+///Returns the number of clauses commander_exactly_one adds for n inputs
+fn commander_clauses(n: usize) -> usize {
+    let mut clauses = 0;
+    let groups = if n < 6 {
+        n
+    } else {
+        let chunk_size = num::integer::div_ceil(n, 3);
+        let groups = num::integer::div_ceil(n, chunk_size);
+        for i in 0..groups {
+            clauses += commander_clauses(chunk_size.min(n - i * chunk_size));
+        }
+        groups
+    };
+    clauses + groups * groups.saturating_sub(1) / 2 + 1 + groups
+}
+///Returns the number of clauses make_sorting_network adds for n inputs
+fn sorting_network_clauses(n: usize) -> usize {
+    const CLAUSES_PER_SWAP: usize = 6;
+    match base_swaps(n) {
+        Some(swaps) => swaps.len() * CLAUSES_PER_SWAP,
+        None => {
+            let padding_amount = (4 - n % 4) % 4;
+            let m = n + padding_amount;
+            padding_amount
+                + sorting_network_clauses(m - m / 2)
+                + sorting_network_clauses(m / 2)
+                + sorting_network_clauses(m - m / 2)
+                + sorting_network_clauses(m / 2)
+                + (m - 2) / 2 * CLAUSES_PER_SWAP
+        }
+    }
+}
+///Returns the number of clauses add_exactly_one adds for n inputs
+pub fn estimate_clauses_exactly_one(n: usize) -> usize {
+    commander_clauses(n) + 1
+}
+///Returns the number of clauses add_at_most_one adds for n inputs
+pub fn estimate_clauses_at_most_one(n: usize) -> usize {
+    commander_clauses(n) + n
+}
+///Returns the number of auxiliary variables commander_exactly_one adds for n inputs,
+///one commander per group
+///The group sizes involve a ceiling division at every level, so there is no simple closed
+///form. Instead the recursion is followed once per distinct group size of each split,
+///the full groups share one size and only the last may differ.
+pub fn commander_aux_count(n: usize) -> usize {
+    if n < 6 {
+        return 1;
+    }
+    let chunk_size = num::integer::div_ceil(n, 3);
+    let full_chunks = n / chunk_size;
+    let remainder = n % chunk_size;
+    let last = if remainder > 0 {
+        commander_aux_count(remainder)
+    } else {
+        0
+    };
+    1 + full_chunks * commander_aux_count(chunk_size) + last
+}
+///Returns the number of clauses exactly_k adds for n inputs
+///The count is exact, it follows the same recursion as the encoding
+pub fn estimate_clauses_exactly_k(n: usize, k: usize) -> usize {
+    if k == 0 || k == n {
+        n
+    } else if k == 1 {
+        estimate_clauses_exactly_one(n)
+    } else {
+        sorting_network_clauses(n) + 2
+    }
+}
 #[cfg(test)]
 mod tests {
     use varisat::{CnfFormula, ExtendFormula, Lit, Solver};
 
-    use crate::{add_at_most_one, add_exactly_one, exactly_k, make_sorting_network};
+    use crate::{
+        add_at_most_one, add_at_most_one_bitwise, add_at_most_one_by_key, add_at_most_one_pairwise,
+        add_at_most_one_product, add_at_most_one_with, add_conditional_exactly_one,
+        add_exactly_one, add_exactly_one_pair, add_group_implies_group, all_models,
+        apply_comparators, at_most_one_with_flag, commander_aux_count, commander_exactly_one,
+        commander_exactly_one_min_vars, commander_exactly_one_reified, commander_tree,
+        estimate_clauses_at_most_one, estimate_clauses_exactly_k, estimate_clauses_exactly_one,
+        exactly_k, exactly_k_with_exclusions, exactly_one_tree, make_sorting_network,
+        propagate_units, reified_exactly_one, sorting_comparators, AmoEncoding, CommanderChild,
+    };
     fn solve_print(formula: &CnfFormula) -> bool {
         let mut solver = Solver::new();
         solver.add_formula(formula);
@@ -202,6 +736,37 @@ mod tests {
         }
         res
     }
+    //Checks that forcing the n inputs to each assignment in turn leaves both encodings
+    //satisfiable or both unsatisfiable
+    fn assert_equisatisfiable(
+        build_a: impl Fn(&mut CnfFormula, &[Lit]),
+        build_b: impl Fn(&mut CnfFormula, &[Lit]),
+        n: usize,
+    ) {
+        let solver_for = |build: &dyn Fn(&mut CnfFormula, &[Lit])| {
+            let mut formula = CnfFormula::new();
+            let lits: Vec<Lit> = formula.new_lit_iter(n).collect();
+            build(&mut formula, &lits);
+            let mut solver = Solver::new();
+            solver.add_formula(&formula);
+            (solver, lits)
+        };
+        let (mut solver_a, lits_a) = solver_for(&build_a);
+        let (mut solver_b, lits_b) = solver_for(&build_b);
+        for bits in 0..1u64 << n {
+            let mut sat = Vec::new();
+            for (solver, lits) in [(&mut solver_a, &lits_a), (&mut solver_b, &lits_b)] {
+                let assumptions: Vec<Lit> = lits
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &lit)| if bits >> i & 1 == 1 { lit } else { !lit })
+                    .collect();
+                solver.assume(&assumptions);
+                sat.push(solver.solve().unwrap());
+            }
+            assert_eq!(sat[0], sat[1], "n={} inputs={:b}", n, bits);
+        }
+    }
     #[test]
     fn basic_test() {
         let mut formula = CnfFormula::new();
@@ -245,6 +810,99 @@ mod tests {
         println!("solution is {}", res);
         assert!(!res);
     }
+    //Checks by the zero-one principle that the network sorts every input pattern
+    fn assert_network_sorts(n: usize) {
+        assert_sorts_with(n, make_sorting_network);
+    }
+    fn assert_sorts_with(n: usize, build: impl Fn(&mut CnfFormula, &[Lit]) -> Vec<Lit>) {
+        let mut formula = CnfFormula::new();
+        let lits: Vec<Lit> = formula.new_lit_iter(n).collect();
+        let sorted_lits = build(&mut formula, &lits);
+        assert_eq!(sorted_lits.len(), n, "size {}", n);
+        let mut solver = Solver::new();
+        solver.add_formula(&formula);
+        for bits in 0..1u32 << n {
+            let inputs: Vec<Lit> = (0..n)
+                .map(|i| {
+                    if bits >> i & 1 == 1 {
+                        lits[i]
+                    } else {
+                        !lits[i]
+                    }
+                })
+                .collect();
+            solver.assume(&inputs);
+            assert!(solver.solve().unwrap(), "size {} input {:b}", n, bits);
+            let model = solver.model().unwrap();
+            let ones = bits.count_ones() as usize;
+            for (i, out) in sorted_lits.iter().enumerate() {
+                assert_eq!(
+                    model.contains(out),
+                    i >= n - ones,
+                    "size {} input {:b} output {}",
+                    n,
+                    bits,
+                    i
+                );
+            }
+        }
+    }
+    #[test]
+    fn sorts_size_0() {
+        assert_network_sorts(0);
+    }
+    #[test]
+    fn sorts_size_1() {
+        assert_network_sorts(1);
+    }
+    #[test]
+    fn sorts_size_2() {
+        assert_network_sorts(2);
+    }
+    #[test]
+    fn sorts_size_3() {
+        assert_network_sorts(3);
+    }
+    #[test]
+    fn sorts_size_4() {
+        assert_network_sorts(4);
+    }
+    #[test]
+    fn sorts_size_5() {
+        assert_network_sorts(5);
+    }
+    #[test]
+    fn sorts_size_6() {
+        assert_network_sorts(6);
+    }
+    #[test]
+    fn sorts_size_7() {
+        assert_network_sorts(7);
+    }
+    #[test]
+    fn odd_sort_test() {
+        for n in [9, 11, 13] {
+            for i in 0..n - 1 {
+                let mut formula = CnfFormula::new();
+                let lits: Vec<Lit> = formula.new_lit_iter(n).collect();
+                let sorted_lits = make_sorting_network(&mut formula, &lits);
+                assert_eq!(sorted_lits.len(), n);
+                formula.add_clause(&[sorted_lits[i]]);
+                formula.add_clause(&[!sorted_lits[i + 1]]);
+                assert!(!solve_print(&formula), "size {} unsorted at {}", n, i);
+            }
+        }
+    }
+    #[test]
+    fn exclusions_test() {
+        let mut formula = CnfFormula::new();
+        let lits: Vec<Lit> = formula.new_lit_iter(4).collect();
+        exactly_k_with_exclusions(&mut formula, &lits, 2, &[(0, 1), (2, 3)]);
+        assert!(solve_print(&formula));
+        formula.add_clause(&[!lits[2]]);
+        formula.add_clause(&[!lits[3]]);
+        assert!(!solve_print(&formula));
+    }
     #[test]
     fn big_test() {
         let mut formula = CnfFormula::new();
@@ -308,6 +966,197 @@ mod tests {
         assert!(solve_print(&formula));
     }
     #[test]
+    fn pairwise_at_most_one() {
+        let mut formula = CnfFormula::new();
+        let lits: Vec<Lit> = formula.new_lit_iter(6).collect();
+        let var_count = formula.var_count();
+        add_at_most_one_pairwise(&mut formula, &lits);
+        assert_eq!(var_count, formula.var_count());
+        assert_eq!(formula.len(), 15);
+        formula.add_clause(&[lits[1]]);
+        assert!(solve_print(&formula));
+        formula.add_clause(&[lits[4]]);
+        assert!(!solve_print(&formula));
+    }
+    #[test]
+    fn group_implies_group() {
+        let mut formula = CnfFormula::new();
+        let a: Vec<Lit> = formula.new_lit_iter(3).collect();
+        let b: Vec<Lit> = formula.new_lit_iter(3).collect();
+        add_group_implies_group(&mut formula, &a, &b);
+        for &lit in &a {
+            let mut solver = Solver::new();
+            solver.add_formula(&formula);
+            solver.assume(&[lit]);
+            assert!(solver.solve().unwrap());
+            let model = solver.model().unwrap();
+            assert!(b.iter().any(|l| model.contains(l)));
+        }
+        let mut formula = CnfFormula::new();
+        let a: Vec<Lit> = formula.new_lit_iter(3).collect();
+        add_group_implies_group(&mut formula, &a, &[]);
+        assert!(solve_print(&formula));
+        formula.add_clause(&[a[1]]);
+        assert!(!solve_print(&formula));
+    }
+    #[test]
+    fn clause_estimates() {
+        for n in [0, 1, 2, 5, 6, 7, 9, 10, 17, 30, 100] {
+            let mut formula = CnfFormula::new();
+            let lits: Vec<Lit> = formula.new_lit_iter(n).collect();
+            add_exactly_one(&mut formula, &lits);
+            assert_eq!(formula.len(), estimate_clauses_exactly_one(n));
+            let mut formula = CnfFormula::new();
+            let lits: Vec<Lit> = formula.new_lit_iter(n).collect();
+            add_at_most_one(&mut formula, &lits);
+            assert_eq!(formula.len(), estimate_clauses_at_most_one(n));
+        }
+        for (n, k) in [
+            (0, 0),
+            (4, 0),
+            (4, 4),
+            (6, 1),
+            (3, 2),
+            (7, 3),
+            (9, 4),
+            (20, 5),
+            (33, 30),
+        ] {
+            let mut formula = CnfFormula::new();
+            let lits: Vec<Lit> = formula.new_lit_iter(n).collect();
+            exactly_k(&mut formula, &lits, k);
+            assert_eq!(
+                formula.len(),
+                estimate_clauses_exactly_k(n, k),
+                "n={} k={}",
+                n,
+                k
+            );
+        }
+    }
+    #[test]
+    fn commander_tree_shape() {
+        let mut formula = CnfFormula::new();
+        let lits: Vec<Lit> = formula.new_lit_iter(9).collect();
+        let tree = commander_tree(&mut formula, &lits);
+        assert_eq!(tree.children.len(), 3);
+        for (i, child) in tree.children.iter().enumerate() {
+            match child {
+                CommanderChild::Node(node) => {
+                    let expected: Vec<CommanderChild> =
+                        (3 * i..3 * i + 3).map(CommanderChild::Input).collect();
+                    assert_eq!(node.children, expected);
+                }
+                CommanderChild::Input(_) => panic!("expected a subgroup"),
+            }
+        }
+        formula.add_clause(&[tree.commander]);
+        formula.add_clause(&[lits[7]]);
+        let mut solver = Solver::new();
+        solver.add_formula(&formula);
+        assert!(solver.solve().unwrap());
+        let model = solver.model().unwrap();
+        for (i, child) in tree.children.iter().enumerate() {
+            if let CommanderChild::Node(node) = child {
+                assert_eq!(model.contains(&node.commander), i == 2);
+            }
+        }
+    }
+    #[test]
+    fn product_at_most_one() {
+        for n in 0..12 {
+            for i in 0..n {
+                for j in 0..=i {
+                    let mut formula = CnfFormula::new();
+                    let lits: Vec<Lit> = formula.new_lit_iter(n).collect();
+                    add_at_most_one_product(&mut formula, &lits);
+                    formula.add_clause(&[lits[i]]);
+                    formula.add_clause(&[lits[j]]);
+                    assert_eq!(solve_print(&formula), i == j);
+                }
+            }
+        }
+        let mut formula = CnfFormula::new();
+        let lits: Vec<Lit> = formula.new_lit_iter(100).collect();
+        add_at_most_one_product(&mut formula, &lits);
+        //10 row and 10 column selectors, each laid out with 3 + 4 more
+        assert_eq!(formula.var_count(), 134);
+        formula.add_clause(&[lits[37]]);
+        assert!(solve_print(&formula));
+        formula.add_clause(&[lits[73]]);
+        assert!(!solve_print(&formula));
+    }
+    //Returns which values the literal can take with exactly the given inputs true
+    fn possible_values(
+        formula: &CnfFormula,
+        inputs: &[Lit],
+        true_inputs: &[usize],
+        lit: Lit,
+    ) -> (bool, bool) {
+        let mut solver = Solver::new();
+        solver.add_formula(formula);
+        let mut assumptions: Vec<Lit> = inputs
+            .iter()
+            .enumerate()
+            .map(|(i, &input)| {
+                if true_inputs.contains(&i) {
+                    input
+                } else {
+                    !input
+                }
+            })
+            .collect();
+        assumptions.push(lit);
+        solver.assume(&assumptions);
+        let can_be_true = solver.solve().unwrap();
+        *assumptions.last_mut().unwrap() = !lit;
+        solver.assume(&assumptions);
+        (can_be_true, solver.solve().unwrap())
+    }
+    #[test]
+    fn commander_pinned() {
+        for n in [1, 4, 9, 20] {
+            let mut formula = CnfFormula::new();
+            let lits: Vec<Lit> = formula.new_lit_iter(n).collect();
+            let commander = commander_exactly_one(&mut formula, &lits);
+            //With no input true the commander must be false
+            assert_eq!(
+                possible_values(&formula, &lits, &[], commander),
+                (false, true)
+            );
+            for i in 0..n {
+                //With one input true the commander must be true
+                assert_eq!(
+                    possible_values(&formula, &lits, &[i], commander),
+                    (true, false)
+                );
+                for j in 0..i {
+                    //Two true inputs are ruled out whatever the commander
+                    assert_eq!(
+                        possible_values(&formula, &lits, &[i, j], commander),
+                        (false, false)
+                    );
+                }
+            }
+        }
+    }
+    #[test]
+    fn reified_exactly_one_values() {
+        for n in [0, 1, 2, 5, 9] {
+            let mut formula = CnfFormula::new();
+            let lits: Vec<Lit> = formula.new_lit_iter(n).collect();
+            let r = reified_exactly_one(&mut formula, &lits);
+            assert_eq!(formula.var_count(), n + 3 * n + 1);
+            assert_eq!(possible_values(&formula, &lits, &[], r), (false, true));
+            for i in 0..n {
+                assert_eq!(possible_values(&formula, &lits, &[i], r), (true, false));
+                for j in 0..i {
+                    assert_eq!(possible_values(&formula, &lits, &[i, j], r), (false, true));
+                }
+            }
+        }
+    }
+    #[test]
     fn unsat_at_most_one() {
         let mut formula = CnfFormula::new();
         let lits: Vec<Lit> = formula.new_lit_iter(1000).collect();
@@ -316,4 +1165,283 @@ mod tests {
         formula.add_clause(&[lits[337]]);
         assert!(!solve_print(&formula));
     }
+    #[test]
+    fn at_most_one_flag() {
+        for n in [0, 1, 4, 9] {
+            let mut formula = CnfFormula::new();
+            let lits: Vec<Lit> = formula.new_lit_iter(n).collect();
+            let flag = at_most_one_with_flag(&mut formula, &lits);
+            assert_eq!(possible_values(&formula, &lits, &[], flag), (false, true));
+            for i in 0..n {
+                assert_eq!(possible_values(&formula, &lits, &[i], flag), (true, false));
+            }
+            if n > 1 {
+                let mut two = CnfFormula::from(formula.iter());
+                two.add_clause(&[lits[0]]);
+                two.add_clause(&[lits[n - 1]]);
+                let mut solver = Solver::new();
+                solver.add_formula(&two);
+                assert!(!solver.solve().unwrap());
+            }
+        }
+    }
+    #[test]
+    fn amo_encodings_agree() {
+        let encodings = [
+            AmoEncoding::Commander,
+            AmoEncoding::Pairwise,
+            AmoEncoding::Bitwise,
+            AmoEncoding::Product,
+            AmoEncoding::SequentialCounter,
+        ];
+        let forced: [&[usize]; 6] = [&[], &[0], &[7], &[3, 4], &[0, 7], &[1, 2, 6]];
+        for enc in encodings {
+            for n in [0, 1, 2, 5] {
+                let mut formula = CnfFormula::new();
+                let lits: Vec<Lit> = formula.new_lit_iter(n).collect();
+                add_at_most_one_with(&mut formula, &lits, enc);
+                assert!(solve_print(&formula), "{:?} n={}", enc, n);
+            }
+            for true_inputs in forced {
+                let mut formula = CnfFormula::new();
+                let lits: Vec<Lit> = formula.new_lit_iter(8).collect();
+                add_at_most_one_with(&mut formula, &lits, enc);
+                for &i in true_inputs {
+                    formula.add_clause(&[lits[i]]);
+                }
+                assert_eq!(
+                    solve_print(&formula),
+                    true_inputs.len() <= 1,
+                    "{:?} {:?}",
+                    enc,
+                    true_inputs
+                );
+            }
+        }
+    }
+    #[test]
+    fn commander_reified() {
+        for n in [0, 1, 2, 5, 6, 13] {
+            let mut formula = CnfFormula::new();
+            let lits: Vec<Lit> = formula.new_lit_iter(n).collect();
+            let flag = commander_exactly_one_reified(&mut formula, &lits);
+            assert_eq!(possible_values(&formula, &lits, &[], flag), (false, true));
+            for i in 0..n {
+                assert_eq!(possible_values(&formula, &lits, &[i], flag), (true, false));
+                for j in 0..i {
+                    assert_eq!(
+                        possible_values(&formula, &lits, &[i, j], flag),
+                        (false, true)
+                    );
+                }
+            }
+        }
+    }
+    #[test]
+    fn exactly_one_pair() {
+        let mut formula = CnfFormula::new();
+        let (a, b, c, d) = formula.new_lits();
+        let pairs = [(a, b), (b, c), (c, d)];
+        add_exactly_one_pair(&mut formula, &pairs);
+        let models = all_models(&formula, &[a, b, c, d], None);
+        for model in &models {
+            let holding = pairs
+                .iter()
+                .filter(|&&(x, y)| model[x.index()] && model[y.index()])
+                .count();
+            assert_eq!(holding, 1, "{:?}", model);
+        }
+        //The models of a, b, c, d are 1100, 1101, 0110, 0011 and 1011
+        assert_eq!(models.len(), 5);
+        formula.add_clause(&[b]);
+        formula.add_clause(&[c]);
+        assert_eq!(
+            all_models(&formula, &[a, b, c, d], None),
+            vec![vec![false, true, true, false]]
+        );
+        formula.add_clause(&[d]);
+        assert!(!solve_print(&formula));
+    }
+    #[test]
+    fn exactly_k_propagates() {
+        for (n, k) in [(2, 1), (5, 2), (8, 3), (9, 4), (13, 6), (20, 7)] {
+            for start in [0, n - k] {
+                let mut formula = CnfFormula::new();
+                let lits: Vec<Lit> = formula.new_lit_iter(n).collect();
+                exactly_k(&mut formula, &lits, k);
+                let chosen = &lits[start..start + k];
+                //Forcing k inputs true must propagate the others false, and forcing
+                //n - k inputs false must propagate the others true
+                let mut ones = CnfFormula::from(formula.iter());
+                for &lit in chosen {
+                    ones.add_clause(&[lit]);
+                }
+                let (forced, conflict) = propagate_units(&ones);
+                assert!(!conflict);
+                for &lit in lits.iter().filter(|lit| !chosen.contains(lit)) {
+                    assert!(forced.contains(&!lit), "n={} k={}", n, k);
+                }
+                let mut zeros = CnfFormula::from(formula.iter());
+                for &lit in lits.iter().filter(|lit| !chosen.contains(lit)) {
+                    zeros.add_clause(&[!lit]);
+                }
+                let (forced, conflict) = propagate_units(&zeros);
+                assert!(!conflict);
+                for &lit in chosen {
+                    assert!(forced.contains(&lit), "n={} k={}", n, k);
+                }
+            }
+        }
+    }
+    #[test]
+    fn exactly_one_subgroups() {
+        let mut formula = CnfFormula::new();
+        let lits: Vec<Lit> = formula.new_lit_iter(9).collect();
+        let (top, groups) = exactly_one_tree(&mut formula, &lits);
+        let ranges: Vec<_> = groups.iter().map(|(range, _)| range.clone()).collect();
+        assert_eq!(ranges, vec![0..3, 3..6, 6..9]);
+        for (chosen, &lit) in lits.iter().enumerate() {
+            let mut solver = Solver::new();
+            solver.add_formula(&formula);
+            solver.assume(&[lit]);
+            assert!(solver.solve().unwrap());
+            let model = solver.model().unwrap();
+            assert!(model.contains(&top));
+            for (range, commander) in &groups {
+                assert_eq!(model.contains(commander), range.contains(&chosen));
+            }
+        }
+    }
+    #[test]
+    fn commander_aux_counts() {
+        for n in [0, 1, 5, 6, 7, 9, 17, 20, 64, 100, 1000] {
+            let mut formula = CnfFormula::new();
+            let lits: Vec<Lit> = formula.new_lit_iter(n).collect();
+            commander_exactly_one(&mut formula, &lits);
+            assert_eq!(formula.var_count() - n, commander_aux_count(n), "n={}", n);
+        }
+    }
+    #[test]
+    fn commander_min_vars() {
+        for n in 7..=16 {
+            let mut default = CnfFormula::new();
+            let lits: Vec<Lit> = default.new_lit_iter(n).collect();
+            commander_exactly_one(&mut default, &lits);
+            let mut formula = CnfFormula::new();
+            let lits: Vec<Lit> = formula.new_lit_iter(n).collect();
+            commander_exactly_one_min_vars(&mut formula, &lits);
+            assert!(formula.var_count() <= default.var_count(), "n={}", n);
+        }
+        for n in [0, 1, 5, 6, 7, 9, 12] {
+            let mut formula = CnfFormula::new();
+            let lits: Vec<Lit> = formula.new_lit_iter(n).collect();
+            let commander = commander_exactly_one_min_vars(&mut formula, &lits);
+            for bits in 0..1u32 << n {
+                let mut solver = Solver::new();
+                solver.add_formula(&formula);
+                let assumptions: Vec<Lit> = lits
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &lit)| if bits >> i & 1 == 1 { lit } else { !lit })
+                    .collect();
+                solver.assume(&assumptions);
+                let sat = solver.solve().unwrap();
+                assert_eq!(sat, bits.count_ones() <= 1, "n={} bits={:b}", n, bits);
+                if sat {
+                    let model = solver.model().unwrap();
+                    assert_eq!(model.contains(&commander), bits.count_ones() == 1);
+                }
+            }
+        }
+    }
+    #[test]
+    fn amo_encodings_equisatisfiable() {
+        for n in 0..=8 {
+            assert_equisatisfiable(add_at_most_one_pairwise, add_at_most_one, n);
+            assert_equisatisfiable(add_at_most_one_pairwise, add_at_most_one_bitwise, n);
+            assert_equisatisfiable(add_at_most_one_pairwise, add_at_most_one_product, n);
+        }
+    }
+    #[test]
+    fn at_most_one_propagates() {
+        for n in [2, 5, 6, 7, 17, 20, 100] {
+            let mut formula = CnfFormula::new();
+            let lits: Vec<Lit> = formula.new_lit_iter(n).collect();
+            add_at_most_one(&mut formula, &lits);
+            for (i, &chosen) in lits.iter().enumerate() {
+                let mut forced_one = CnfFormula::from(formula.iter());
+                forced_one.add_clause(&[chosen]);
+                let (forced, conflict) = propagate_units(&forced_one);
+                assert!(!conflict);
+                for &lit in lits.iter().filter(|&&lit| lit != chosen) {
+                    assert!(forced.contains(&!lit), "n={} i={}", n, i);
+                }
+            }
+        }
+    }
+    #[test]
+    fn reused_comparators() {
+        for n in [6, 10, 13] {
+            let comparators = sorting_comparators(n);
+            assert_sorts_with(n, |formula, lits| {
+                apply_comparators(formula, lits, &comparators)
+            });
+        }
+        //The same topology sorts two vectors independently
+        let comparators = sorting_comparators(6);
+        let mut formula = CnfFormula::new();
+        let a: Vec<Lit> = formula.new_lit_iter(6).collect();
+        let b: Vec<Lit> = formula.new_lit_iter(6).collect();
+        let sorted_a = apply_comparators(&mut formula, &a, &comparators);
+        let sorted_b = apply_comparators(&mut formula, &b, &comparators);
+        formula.add_clause(&[a[0]]);
+        formula.add_clause(&[sorted_b[0]]);
+        let (forced, conflict) = propagate_units(&formula);
+        assert!(!conflict);
+        assert!(forced.contains(&sorted_a[5]));
+        assert!(b.iter().all(|lit| forced.contains(lit)));
+    }
+    #[test]
+    fn conditional_exactly_one() {
+        for cond_value in [true, false] {
+            let mut formula = CnfFormula::new();
+            let cond = formula.new_lit();
+            let if_true: Vec<Lit> = formula.new_lit_iter(4).collect();
+            let if_false: Vec<Lit> = formula.new_lit_iter(7).collect();
+            add_conditional_exactly_one(&mut formula, cond, &if_true, &if_false);
+            formula.add_clause(&[if cond_value { cond } else { !cond }]);
+            let (chosen, other) = if cond_value {
+                (&if_true, &if_false)
+            } else {
+                (&if_false, &if_true)
+            };
+            let models = all_models(&formula, chosen, None);
+            assert_eq!(models.len(), chosen.len());
+            assert!(models
+                .iter()
+                .all(|model| model.iter().filter(|&&value| value).count() == 1));
+            assert_eq!(
+                all_models(&formula, other, None),
+                vec![vec![false; other.len()]]
+            );
+        }
+    }
+    #[test]
+    fn at_most_one_by_key() {
+        let mut formula = CnfFormula::new();
+        let lits: Vec<Lit> = formula.new_lit_iter(6).collect();
+        let items: Vec<(&str, Lit)> = lits
+            .iter()
+            .enumerate()
+            .map(|(i, &lit)| (if i % 2 == 0 { "even" } else { "odd" }, lit))
+            .collect();
+        add_at_most_one_by_key(&mut formula, &items);
+        let mut same_key = CnfFormula::from(formula.iter());
+        same_key.add_clause(&[lits[0]]);
+        same_key.add_clause(&[lits[4]]);
+        assert!(!solve_print(&same_key));
+        formula.add_clause(&[lits[0]]);
+        formula.add_clause(&[lits[3]]);
+        assert!(solve_print(&formula));
+    }
 }
@@ -0,0 +1,293 @@
+use varisat::{CnfFormula, ExtendFormula, Lit};
+
+use crate::{add_at_most_one, add_equal, and_gate, exactly_k, or_gate};
+
+///Adds clauses requiring exactly k distinct values to be used across the one-hot variables
+///Each variable is a slice with one literal per value, all of the same length.
+///The variables are not required to be one-hot by this function.
+///Adds auxiliary variables: one "value used" literal per value, plus those of exactly_k
+pub fn add_distinct_values_exactly_k(formula: &mut CnfFormula, vars: &[&[Lit]], k: usize) {
+    let domain = vars.first().map_or(0, |var| var.len());
+    assert!(
+        vars.iter().all(|var| var.len() == domain),
+        "all variables must share a domain"
+    );
+    let used: Vec<Lit> = (0..domain)
+        .map(|value| {
+            let holders: Vec<Lit> = vars.iter().map(|var| var[value]).collect();
+            or_gate(formula, &holders)
+        })
+        .collect();
+    exactly_k(formula, &used, k);
+}
+///Adds clauses requiring the one-hot variables to take pairwise different values
+///Each variable is a slice with one literal per value, all of the same length.
+///The variables are not required to be one-hot by this function.
+///Adds auxiliary variables: those of add_at_most_one per value
+pub fn add_all_different(formula: &mut CnfFormula, vars: &[&[Lit]]) {
+    let domain = vars.first().map_or(0, |var| var.len());
+    assert!(
+        vars.iter().all(|var| var.len() == domain),
+        "all variables must share a domain"
+    );
+    for value in 0..domain {
+        let holders: Vec<Lit> = vars.iter().map(|var| var[value]).collect();
+        add_at_most_one(formula, &holders);
+    }
+}
+///Adds clauses restricting a one-hot variable to the values from lo to hi inclusive
+///Panics unless lo <= hi < the domain size.
+///Adds no auxiliary variables
+pub fn add_value_in_range(formula: &mut CnfFormula, one_hot: &[Lit], lo: usize, hi: usize) {
+    assert!(lo <= hi, "empty value range");
+    assert!(hi < one_hot.len(), "value range out of bounds");
+    for (value, &lit) in one_hot.iter().enumerate() {
+        if value < lo || value > hi {
+            formula.add_clause(&[!lit]);
+        }
+    }
+}
+///Adds clauses requiring the one-hot variables to take pairwise different values that
+///together form a run of consecutive values
+///The minimum and maximum value are order encoded, with a literal per value t true iff they
+///are at least t, and the maximum is required to be the minimum plus len(vars) - 1.
+///The variables are not required to be one-hot by this function.
+///Adds auxiliary variables: an "at least t" literal per variable and value, one each for the
+///minimum and maximum per value, plus those of add_all_different
+pub fn add_consecutive_distinct(formula: &mut CnfFormula, vars: &[&[Lit]]) {
+    add_all_different(formula, vars);
+    let domain = vars.first().map_or(0, |var| var.len());
+    let at_least: Vec<Vec<Lit>> = vars
+        .iter()
+        .map(|var| (0..domain).map(|t| or_gate(formula, &var[t..])).collect())
+        .collect();
+    let min_at_least: Vec<Lit> = (0..domain)
+        .map(|t| {
+            let column: Vec<Lit> = at_least.iter().map(|var| var[t]).collect();
+            and_gate(formula, &column)
+        })
+        .collect();
+    let max_at_least: Vec<Lit> = (0..domain)
+        .map(|t| {
+            let column: Vec<Lit> = at_least.iter().map(|var| var[t]).collect();
+            or_gate(formula, &column)
+        })
+        .collect();
+    //The minimum is at least t iff the maximum is at least t + span, which is impossible
+    //past the end of the domain
+    let span = vars.len().saturating_sub(1);
+    for (t, &min) in min_at_least.iter().enumerate() {
+        match max_at_least.get(t + span) {
+            Some(&max) => add_equal(formula, min, max),
+            None => formula.add_clause(&[!min]),
+        }
+    }
+}
+fn assert_tuple_values(vars: &[&[Lit]], tuple: &[usize]) {
+    assert!(
+        tuple.len() == vars.len(),
+        "one value per variable in each tuple"
+    );
+    for (var, &value) in vars.iter().zip(tuple) {
+        assert!(value < var.len(), "tuple value out of the domain");
+    }
+}
+///Adds clauses requiring the one-hot variables to take the values of one of the allowed
+///tuples, each listing a value per variable
+///The variables are not required to be one-hot by this function.
+///Adds auxiliary variables: one "tuple holds" literal per allowed tuple
+pub fn add_table_constraint(formula: &mut CnfFormula, vars: &[&[Lit]], allowed: &[Vec<usize>]) {
+    let holds: Vec<Lit> = allowed
+        .iter()
+        .map(|tuple| {
+            assert_tuple_values(vars, tuple);
+            let selected: Vec<Lit> = vars
+                .iter()
+                .zip(tuple)
+                .map(|(var, &value)| var[value])
+                .collect();
+            and_gate(formula, &selected)
+        })
+        .collect();
+    formula.add_clause(&holds);
+}
+///Adds clauses ruling out the forbidden tuples of values of the one-hot variables, each
+///listing a value per variable
+///Adds no auxiliary variables
+pub fn add_forbidden_tuples(formula: &mut CnfFormula, vars: &[&[Lit]], forbidden: &[Vec<usize>]) {
+    for tuple in forbidden {
+        assert_tuple_values(vars, tuple);
+        let clause: Vec<Lit> = vars
+            .iter()
+            .zip(tuple)
+            .map(|(var, &value)| !var[value])
+            .collect();
+        formula.add_clause(&clause);
+    }
+}
+///Returns the position of the true literal of a one-hot variable in the model,
+///or None if the model does not make exactly one of its literals true
+pub fn decode_one_hot(var: &[Lit], model: &[Lit]) -> Option<usize> {
+    let mut true_values = var
+        .iter()
+        .enumerate()
+        .filter(|(_, lit)| model.contains(lit))
+        .map(|(value, _)| value);
+    match (true_values.next(), true_values.next()) {
+        (Some(value), None) => Some(value),
+        _ => None,
+    }
+}
+#[cfg(test)]
+mod tests {
+    use varisat::{CnfFormula, ExtendFormula, Lit};
+
+    use crate::test_util::solve;
+    use crate::{
+        add_all_different, add_consecutive_distinct, add_distinct_values_exactly_k,
+        add_exactly_one, add_forbidden_tuples, add_table_constraint, add_value_in_range,
+        all_models, decode_one_hot,
+    };
+    fn one_hots(formula: &mut CnfFormula, count: usize, domain: usize) -> Vec<Vec<Lit>> {
+        (0..count)
+            .map(|_| {
+                let var: Vec<Lit> = formula.new_lit_iter(domain).collect();
+                add_exactly_one(formula, &var);
+                var
+            })
+            .collect()
+    }
+    #[test]
+    fn two_distinct_values() {
+        let mut formula = CnfFormula::new();
+        let vars = one_hots(&mut formula, 3, 3);
+        let slices: Vec<&[Lit]> = vars.iter().map(|var| &var[..]).collect();
+        add_distinct_values_exactly_k(&mut formula, &slices, 2);
+        let model = solve(&formula).unwrap();
+        let used = (0..3)
+            .filter(|&value| vars.iter().any(|var| model.contains(&var[value])))
+            .count();
+        assert_eq!(used, 2);
+        let mut all_same = CnfFormula::from(formula.iter());
+        for var in &vars {
+            all_same.add_clause(&[var[1]]);
+        }
+        assert!(solve(&all_same).is_none());
+        let mut all_different = CnfFormula::from(formula.iter());
+        for (value, var) in vars.iter().enumerate() {
+            all_different.add_clause(&[var[value]]);
+        }
+        assert!(solve(&all_different).is_none());
+        formula.add_clause(&[vars[0][2]]);
+        formula.add_clause(&[vars[1][0]]);
+        formula.add_clause(&[vars[2][2]]);
+        assert!(solve(&formula).is_some());
+    }
+    #[test]
+    fn decode_values() {
+        let mut formula = CnfFormula::new();
+        let var: Vec<Lit> = formula.new_lit_iter(3).collect();
+        assert_eq!(decode_one_hot(&var, &[!var[0], var[1], !var[2]]), Some(1));
+        assert_eq!(decode_one_hot(&var, &[!var[0], !var[1], !var[2]]), None);
+        assert_eq!(decode_one_hot(&var, &[var[0], !var[1], var[2]]), None);
+    }
+    #[test]
+    fn all_different() {
+        let mut formula = CnfFormula::new();
+        let vars = one_hots(&mut formula, 3, 3);
+        let slices: Vec<&[Lit]> = vars.iter().map(|var| &var[..]).collect();
+        add_all_different(&mut formula, &slices);
+        let model = solve(&formula).unwrap();
+        let mut values: Vec<usize> = vars
+            .iter()
+            .map(|var| decode_one_hot(var, &model).unwrap())
+            .collect();
+        values.sort();
+        assert_eq!(values, vec![0, 1, 2]);
+        formula.add_clause(&[vars[0][1]]);
+        formula.add_clause(&[vars[2][1]]);
+        assert!(solve(&formula).is_none());
+    }
+    #[test]
+    fn value_in_range() {
+        let mut formula = CnfFormula::new();
+        let vars = one_hots(&mut formula, 1, 5);
+        add_value_in_range(&mut formula, &vars[0], 1, 3);
+        let values: Vec<usize> = all_models(&formula, &vars[0], None)
+            .iter()
+            .map(|model| model.iter().position(|&value| value).unwrap())
+            .collect();
+        assert_eq!(values.len(), 3);
+        assert!(values.iter().all(|value| (1..=3).contains(value)));
+    }
+    #[test]
+    #[should_panic]
+    fn value_range_out_of_bounds() {
+        let mut formula = CnfFormula::new();
+        let vars = one_hots(&mut formula, 1, 5);
+        add_value_in_range(&mut formula, &vars[0], 2, 5);
+    }
+    fn value_tuples(formula: &CnfFormula, vars: &[Vec<Lit>]) -> Vec<Vec<usize>> {
+        let lits: Vec<Lit> = vars.iter().flatten().copied().collect();
+        let mut tuples: Vec<Vec<usize>> = all_models(formula, &lits, None)
+            .iter()
+            .map(|model| {
+                let mut offset = 0;
+                vars.iter()
+                    .map(|var| {
+                        let values = &model[offset..offset + var.len()];
+                        offset += var.len();
+                        values.iter().position(|&value| value).unwrap()
+                    })
+                    .collect()
+            })
+            .collect();
+        tuples.sort();
+        tuples
+    }
+    #[test]
+    fn table_constraint() {
+        let mut formula = CnfFormula::new();
+        let vars = one_hots(&mut formula, 2, 3);
+        let slices: Vec<&[Lit]> = vars.iter().map(|var| &var[..]).collect();
+        add_table_constraint(&mut formula, &slices, &[vec![0, 1], vec![2, 2]]);
+        assert_eq!(value_tuples(&formula, &vars), vec![vec![0, 1], vec![2, 2]]);
+        add_table_constraint(&mut formula, &slices, &[]);
+        assert!(solve(&formula).is_none());
+    }
+    #[test]
+    fn forbidden_tuples() {
+        let mut formula = CnfFormula::new();
+        let vars = one_hots(&mut formula, 2, 2);
+        let slices: Vec<&[Lit]> = vars.iter().map(|var| &var[..]).collect();
+        add_forbidden_tuples(&mut formula, &slices, &[vec![0, 0]]);
+        assert_eq!(
+            value_tuples(&formula, &vars),
+            vec![vec![0, 1], vec![1, 0], vec![1, 1]]
+        );
+    }
+    #[test]
+    fn consecutive_distinct() {
+        for (values, allowed) in [
+            ([1, 2, 3], true),
+            ([3, 1, 2], true),
+            ([1, 2, 4], false),
+            ([2, 2, 3], false),
+        ] {
+            let mut formula = CnfFormula::new();
+            let vars = one_hots(&mut formula, 3, 5);
+            let slices: Vec<&[Lit]> = vars.iter().map(|var| &var[..]).collect();
+            add_consecutive_distinct(&mut formula, &slices);
+            for (var, &value) in vars.iter().zip(&values) {
+                formula.add_clause(&[var[value]]);
+            }
+            assert_eq!(solve(&formula).is_some(), allowed, "{:?}", values);
+        }
+        let mut formula = CnfFormula::new();
+        let vars = one_hots(&mut formula, 3, 5);
+        let slices: Vec<&[Lit]> = vars.iter().map(|var| &var[..]).collect();
+        add_consecutive_distinct(&mut formula, &slices);
+        //Three starting values for the run, in any of 6 orders
+        assert_eq!(value_tuples(&formula, &vars).len(), 3 * 6);
+    }
+}
@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+
+use varisat::{CnfFormula, ExtendFormula, Lit};
+
+use crate::{add_at_least_one, add_exactly_one};
+
+#[derive(Clone, Copy)]
+enum BddNode {
+    True,
+    False,
+    Lit(Lit),
+}
+//The BDD for a weighted at-most constraint, with nodes memoized on (i, sum)
+struct WeightedAtMost<'a> {
+    vars: &'a [Lit],
+    weights: &'a [u64],
+    //remaining[i] is the total weight of the literals from i on
+    remaining: Vec<u64>,
+    bound: u64,
+    memo: HashMap<(usize, u64), BddNode>,
+}
+impl<'a> WeightedAtMost<'a> {
+    //Returns the node true iff the literals from i on may be chosen with the weight
+    //chosen so far being sum
+    fn node(&mut self, formula: &mut CnfFormula, i: usize, sum: u64) -> BddNode {
+        if sum > self.bound {
+            return BddNode::False;
+        }
+        if sum + self.remaining[i] <= self.bound {
+            return BddNode::True;
+        }
+        if let Some(&node) = self.memo.get(&(i, sum)) {
+            return node;
+        }
+        let with = self.node(formula, i + 1, sum + self.weights[i]);
+        let without = self.node(formula, i + 1, sum);
+        let node = formula.new_lit();
+        //Only the direction needed to assert the root is encoded:
+        //a true node requires the branch taken to be true as well
+        for (branch, taken) in [(with, !self.vars[i]), (without, self.vars[i])] {
+            match branch {
+                BddNode::True => {}
+                BddNode::False => formula.add_clause(&[!node, taken]),
+                BddNode::Lit(lit) => formula.add_clause(&[!node, taken, lit]),
+            }
+        }
+        self.memo.insert((i, sum), BddNode::Lit(node));
+        BddNode::Lit(node)
+    }
+}
+///Adds clauses requiring the total weight of the true literals to be at most bound
+///This uses a BDD over the literals in order, merging nodes with the same partial sum
+///Adds auxiliary variables: one per BDD node
+pub fn add_weighted_at_most(formula: &mut CnfFormula, vars: &[Lit], weights: &[u64], bound: u64) {
+    assert!(vars.len() == weights.len(), "one weight per literal");
+    let mut remaining = vec![0; vars.len() + 1];
+    for i in (0..vars.len()).rev() {
+        remaining[i] = remaining[i + 1] + weights[i];
+    }
+    let mut bdd = WeightedAtMost {
+        vars,
+        weights,
+        remaining,
+        bound,
+        memo: HashMap::new(),
+    };
+    match bdd.node(formula, 0, 0) {
+        BddNode::True => {}
+        BddNode::False => formula.add_clause(&[]),
+        BddNode::Lit(root) => formula.add_clause(&[root]),
+    }
+}
+///Adds clauses requiring the total weight of the true literals to be at least bound
+///This bounds the weight of the false literals from above with add_weighted_at_most.
+///Adds auxiliary variables: one per BDD node
+pub fn add_weighted_at_least(formula: &mut CnfFormula, vars: &[Lit], weights: &[u64], bound: u64) {
+    let total: u64 = weights.iter().sum();
+    if bound > total {
+        formula.add_clause(&[]);
+        return;
+    }
+    let negated: Vec<Lit> = vars.iter().map(|&var| !var).collect();
+    add_weighted_at_most(formula, &negated, weights, total - bound);
+}
+///Adds clauses requiring the selected items to weigh at most max_weight and to be worth
+///at least min_value
+///Adds auxiliary variables: those of the two BDDs
+pub fn add_knapsack(
+    formula: &mut CnfFormula,
+    selected: &[Lit],
+    weights: &[u64],
+    values: &[u64],
+    max_weight: u64,
+    min_value: u64,
+) {
+    add_weighted_at_most(formula, selected, weights, max_weight);
+    add_weighted_at_least(formula, selected, values, min_value);
+}
+///Adds clauses assigning each item to exactly one bin without exceeding the capacity
+///of any bin
+///assignment[i] holds one literal per bin, true iff item i is placed in that bin.
+///Adds auxiliary variables: those of add_exactly_one per item and of the BDD per bin
+pub fn add_bin_packing(
+    formula: &mut CnfFormula,
+    assignment: &[&[Lit]],
+    weights: &[u64],
+    capacities: &[u64],
+) {
+    assert!(assignment.len() == weights.len(), "one weight per item");
+    assert!(
+        assignment.iter().all(|bins| bins.len() == capacities.len()),
+        "one literal per bin for each item"
+    );
+    for bins in assignment {
+        add_exactly_one(formula, bins);
+    }
+    for (bin, &capacity) in capacities.iter().enumerate() {
+        let items: Vec<Lit> = assignment.iter().map(|bins| bins[bin]).collect();
+        add_weighted_at_most(formula, &items, weights, capacity);
+    }
+}
+///Adds clauses requiring every element to be covered by a chosen subset
+///element_covers[e] holds the "subset chosen" literals of the subsets containing element e.
+///Adds no auxiliary variables
+pub fn add_set_cover(formula: &mut CnfFormula, element_covers: &[&[Lit]]) {
+    for covers in element_covers {
+        add_at_least_one(formula, covers);
+    }
+}
+#[cfg(test)]
+mod tests {
+    use varisat::{CnfFormula, ExtendFormula, Lit};
+
+    use crate::test_util::solve;
+    use crate::{
+        add_at_most_k, add_bin_packing, add_knapsack, add_set_cover, add_weighted_at_least,
+        add_weighted_at_most, all_models,
+    };
+    #[test]
+    fn weighted_at_most_exhaustive() {
+        let weights = [3, 1, 4, 1, 5];
+        for bound in 0..16 {
+            for bits in 0..1u32 << weights.len() {
+                let mut formula = CnfFormula::new();
+                let lits: Vec<Lit> = formula.new_lit_iter(weights.len()).collect();
+                add_weighted_at_most(&mut formula, &lits, &weights, bound);
+                let mut total = 0;
+                for (i, &lit) in lits.iter().enumerate() {
+                    if bits >> i & 1 == 1 {
+                        total += weights[i];
+                        formula.add_clause(&[lit]);
+                    } else {
+                        formula.add_clause(&[!lit]);
+                    }
+                }
+                assert_eq!(solve(&formula).is_some(), total <= bound);
+            }
+        }
+    }
+    fn packing(formula: &mut CnfFormula, items: usize, bins: usize) -> Vec<Vec<Lit>> {
+        (0..items)
+            .map(|_| formula.new_lit_iter(bins).collect())
+            .collect()
+    }
+    #[test]
+    fn bin_packing_unsat() {
+        let mut formula = CnfFormula::new();
+        let assignment = packing(&mut formula, 3, 2);
+        let slices: Vec<&[Lit]> = assignment.iter().map(|bins| &bins[..]).collect();
+        add_bin_packing(&mut formula, &slices, &[2, 2, 2], &[3, 3]);
+        assert!(solve(&formula).is_none());
+    }
+    #[test]
+    fn bin_packing_sat() {
+        let mut formula = CnfFormula::new();
+        let assignment = packing(&mut formula, 3, 2);
+        let slices: Vec<&[Lit]> = assignment.iter().map(|bins| &bins[..]).collect();
+        add_bin_packing(&mut formula, &slices, &[2, 2, 2], &[3, 4]);
+        let model = solve(&formula).unwrap();
+        let in_second = assignment
+            .iter()
+            .filter(|bins| model.contains(&bins[1]))
+            .count();
+        assert_eq!(in_second, 2);
+        assert!(assignment
+            .iter()
+            .all(|bins| model.contains(&bins[0]) != model.contains(&bins[1])));
+    }
+    #[test]
+    fn set_cover_needs_both() {
+        let mut formula = CnfFormula::new();
+        let (first, second) = formula.new_lits();
+        //The first subset is {0, 1}, the second {1, 2}
+        add_set_cover(&mut formula, &[&[first], &[first, second], &[second]]);
+        let model = solve(&formula).unwrap();
+        assert!(model.contains(&first) && model.contains(&second));
+        add_at_most_k(&mut formula, &[first, second], 1);
+        assert!(solve(&formula).is_none());
+    }
+    #[test]
+    fn weighted_at_least_exhaustive() {
+        let weights = [2, 7, 1, 8];
+        for bound in 0..20 {
+            for bits in 0..1u32 << weights.len() {
+                let mut formula = CnfFormula::new();
+                let lits: Vec<Lit> = formula.new_lit_iter(weights.len()).collect();
+                add_weighted_at_least(&mut formula, &lits, &weights, bound);
+                let mut total = 0;
+                for (i, &lit) in lits.iter().enumerate() {
+                    if bits >> i & 1 == 1 {
+                        total += weights[i];
+                        formula.add_clause(&[lit]);
+                    } else {
+                        formula.add_clause(&[!lit]);
+                    }
+                }
+                assert_eq!(solve(&formula).is_some(), total >= bound);
+            }
+        }
+    }
+    #[test]
+    fn knapsack() {
+        let weights = [3, 4, 5];
+        let values = [4, 5, 6];
+        let mut formula = CnfFormula::new();
+        let selected: Vec<Lit> = formula.new_lit_iter(3).collect();
+        add_knapsack(&mut formula, &selected, &weights, &values, 8, 11);
+        assert!(solve(&formula).is_none());
+        let mut formula = CnfFormula::new();
+        let selected: Vec<Lit> = formula.new_lit_iter(3).collect();
+        add_knapsack(&mut formula, &selected, &weights, &values, 8, 10);
+        assert_eq!(
+            all_models(&formula, &selected, None),
+            vec![vec![true, false, true]]
+        );
+    }
+}
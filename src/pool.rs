@@ -0,0 +1,103 @@
+use varisat::{CnfFormula, ExtendFormula, Lit};
+
+///Hands out the variables of a formula in order, counting how many each encoding used
+///Variables are numbered from the formula's variable count at creation, so replaying
+///the same calls on a formula of the same size gives the same variable indices.
+pub struct VarPool<'a> {
+    formula: &'a mut CnfFormula,
+    start: usize,
+    consumed: Vec<usize>,
+}
+impl<'a> VarPool<'a> {
+    ///Creates a pool allocating from the formula's next variable on
+    pub fn new(formula: &'a mut CnfFormula) -> VarPool<'a> {
+        let start = formula.var_count();
+        VarPool {
+            formula,
+            start,
+            consumed: Vec::new(),
+        }
+    }
+    ///Returns a fresh literal
+    pub fn fresh(&mut self) -> Lit {
+        self.formula.new_lit()
+    }
+    ///Returns n fresh literals with consecutive variable indices
+    pub fn fresh_block(&mut self, n: usize) -> Vec<Lit> {
+        self.formula.new_lit_iter(n).collect()
+    }
+    ///Runs an encoding on the underlying formula and records how many variables it allocated
+    pub fn encode<T>(&mut self, build: impl FnOnce(&mut CnfFormula) -> T) -> T {
+        let before = self.formula.var_count();
+        let res = build(self.formula);
+        self.consumed.push(self.formula.var_count() - before);
+        res
+    }
+    ///Returns the number of variables allocated by each call to encode, in call order
+    pub fn consumed_by_encodings(&self) -> &[usize] {
+        &self.consumed
+    }
+    ///Returns the number of variables allocated through the pool so far
+    pub fn consumed(&self) -> usize {
+        self.formula.var_count() - self.start
+    }
+}
+///Returns a new formula with count user variables allocated through a VarPool, and their
+///literals
+///Encodings built on the formula afterwards number their auxiliary variables after all
+///the user variables, so reordering how the user variables are used keeps their indices.
+pub fn with_user_vars(count: usize) -> (CnfFormula, Vec<Lit>) {
+    let mut formula = CnfFormula::new();
+    let user_vars = VarPool::new(&mut formula).fresh_block(count);
+    (formula, user_vars)
+}
+#[cfg(test)]
+mod tests {
+    use varisat::{CnfFormula, ExtendFormula, Lit};
+
+    use crate::{add_at_most_one, exactly_k, with_user_vars, VarPool};
+    fn build(formula: &mut CnfFormula) -> (Vec<Lit>, Lit, Vec<usize>, usize) {
+        let mut pool = VarPool::new(formula);
+        let inputs = pool.fresh_block(6);
+        pool.encode(|formula| exactly_k(formula, &inputs, 2));
+        let flag = pool.fresh();
+        pool.encode(|formula| add_at_most_one(formula, &inputs[..4]));
+        let consumed = pool.consumed_by_encodings().to_vec();
+        let total = pool.consumed();
+        (inputs, flag, consumed, total)
+    }
+    #[test]
+    fn deterministic_indices() {
+        let mut first = CnfFormula::new();
+        let mut second = CnfFormula::new();
+        first.new_lit();
+        second.new_lit();
+        let (inputs, flag, consumed, total) = build(&mut first);
+        assert_eq!(
+            build(&mut second),
+            (inputs.clone(), flag, consumed.clone(), total)
+        );
+        assert_eq!(first, second);
+        assert_eq!(inputs[0].index(), 1);
+        assert_eq!(consumed.len(), 2);
+        assert_eq!(total, 6 + 1 + consumed.iter().sum::<usize>());
+        assert_eq!(flag.index(), 7 + consumed[0]);
+    }
+    #[test]
+    fn user_vars_first() {
+        let (mut formula, user_vars) = with_user_vars(5);
+        assert_eq!(formula.var_count(), 5);
+        exactly_k(&mut formula, &user_vars[1..], 2);
+        add_at_most_one(&mut formula, &user_vars[..3]);
+        let last_user = user_vars.iter().map(|lit| lit.index()).max().unwrap();
+        let first_aux = formula
+            .iter()
+            .flatten()
+            .filter(|lit| !user_vars.contains(lit) && !user_vars.contains(&!**lit))
+            .map(|lit| lit.index())
+            .min()
+            .unwrap();
+        assert!(last_user < first_aux);
+        assert!(formula.var_count() > 5);
+    }
+}
@@ -0,0 +1,543 @@
+use std::collections::HashSet;
+
+use varisat::{CnfFormula, ExtendFormula, Lit};
+
+use crate::implication_edges;
+
+///Returns the formula without tautological clauses, duplicate literals within a clause
+///and duplicate clauses
+///Clauses are kept in their original order, with their literals sorted.
+///The result has the same models and the same variable count.
+pub fn simplify(formula: &CnfFormula) -> CnfFormula {
+    let mut simplified = CnfFormula::new();
+    simplified.set_var_count(formula.var_count());
+    let mut seen = HashSet::new();
+    for clause in formula.iter() {
+        let mut clause: Vec<Lit> = clause.to_vec();
+        clause.sort();
+        clause.dedup();
+        //Sorting puts a literal next to its negation
+        if clause.windows(2).any(|pair| pair[0] == !pair[1]) {
+            continue;
+        }
+        if seen.insert(clause.clone()) {
+            simplified.add_clause(&clause);
+        }
+    }
+    simplified
+}
+///Returns whether unit propagation alone proves the formula unsatisfiable
+///This is a cheap check before solving. A false result does not mean the formula is
+///satisfiable.
+pub fn quick_unsat_check(formula: &CnfFormula) -> bool {
+    propagate_units(formula).1
+}
+///Returns the literals forced true by repeated unit propagation, in the order they were
+///forced, and whether propagation reached a conflict, proving the formula unsatisfiable
+pub fn propagate_units(formula: &CnfFormula) -> (Vec<Lit>, bool) {
+    let mut values: Vec<Option<bool>> = vec![None; formula.var_count()];
+    let mut forced = Vec::new();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for clause in formula.iter() {
+            let mut unassigned = None;
+            let mut unassigned_count = 0;
+            let mut satisfied = false;
+            for &lit in clause {
+                match values[lit.index()] {
+                    Some(value) if value == lit.is_positive() => {
+                        satisfied = true;
+                        break;
+                    }
+                    Some(_) => {}
+                    None => {
+                        unassigned = Some(lit);
+                        unassigned_count += 1;
+                    }
+                }
+            }
+            if satisfied {
+                continue;
+            }
+            match (unassigned, unassigned_count) {
+                (None, _) => return (forced, true),
+                (Some(lit), 1) => {
+                    values[lit.index()] = Some(lit.is_positive());
+                    forced.push(lit);
+                    changed = true;
+                }
+                _ => {}
+            }
+        }
+    }
+    (forced, false)
+}
+///Returns the literals whose variable appears in the formula with only one polarity,
+///ordered by variable
+///Setting a pure literal true satisfies every clause it appears in without
+///falsifying any other, so it preserves satisfiability.
+pub fn pure_literals(formula: &CnfFormula) -> Vec<Lit> {
+    //For each variable whether it occurs negatively and positively
+    let mut occurs = vec![[false; 2]; formula.var_count()];
+    for clause in formula.iter() {
+        for &lit in clause {
+            occurs[lit.index()][lit.is_positive() as usize] = true;
+        }
+    }
+    occurs
+        .iter()
+        .enumerate()
+        .filter_map(
+            |(index, &[negative, positive])| match (negative, positive) {
+                (false, true) => Some(Lit::from_index(index, true)),
+                (true, false) => Some(Lit::from_index(index, false)),
+                _ => None,
+            },
+        )
+        .collect()
+}
+//A bit for each literal, hashed into 64 bits, set for the literals of a clause
+//A clause can only be a subset of another if its signature is
+fn signature(clause: &[Lit]) -> u64 {
+    clause
+        .iter()
+        .fold(0, |signature, lit| signature | 1 << (lit.code() % 64))
+}
+//Both clauses must be sorted
+fn is_subset(small: &[Lit], large: &[Lit]) -> bool {
+    let mut large = large.iter();
+    small.iter().all(|lit| large.any(|other| other == lit))
+}
+///Returns the formula without the clauses that contain all literals of another clause
+///Of several identical clauses the first is kept. Clauses are kept in their original
+///order, with their literals sorted and deduplicated.
+///The result has the same models and the same variable count.
+pub fn remove_subsumed(formula: &CnfFormula) -> CnfFormula {
+    let clauses: Vec<Vec<Lit>> = formula
+        .iter()
+        .map(|clause| {
+            let mut clause = clause.to_vec();
+            clause.sort();
+            clause.dedup();
+            clause
+        })
+        .collect();
+    let signatures: Vec<u64> = clauses.iter().map(|clause| signature(clause)).collect();
+    let mut reduced = CnfFormula::new();
+    reduced.set_var_count(formula.var_count());
+    for (i, clause) in clauses.iter().enumerate() {
+        let subsumed = clauses.iter().enumerate().any(|(j, other)| {
+            j != i
+                && signatures[j] & !signatures[i] == 0
+                && (other.len() < clause.len() || (other.len() == clause.len() && j < i))
+                && is_subset(other, clause)
+        });
+        if !subsumed {
+            reduced.add_clause(clause);
+        }
+    }
+    reduced
+}
+//Returns the formula with the literals set true: satisfied clauses are dropped and
+//false literals are removed from the others
+fn assign(formula: &CnfFormula, lits: &[Lit]) -> CnfFormula {
+    let mut values: Vec<Option<bool>> = vec![None; formula.var_count()];
+    for &lit in lits {
+        values[lit.index()] = Some(lit.is_positive());
+    }
+    let mut reduced = CnfFormula::new();
+    reduced.set_var_count(formula.var_count());
+    let mut remaining = Vec::new();
+    for clause in formula.iter() {
+        if clause
+            .iter()
+            .any(|lit| values[lit.index()] == Some(lit.is_positive()))
+        {
+            continue;
+        }
+        remaining.clear();
+        remaining.extend(clause.iter().filter(|lit| values[lit.index()].is_none()));
+        reduced.add_clause(&remaining);
+    }
+    reduced
+}
+///Returns the formula reduced by repeated unit propagation and pure literal elimination,
+///together with the literals fixed along the way
+///The reduced formula no longer mentions the fixed variables and has the same variable
+///count. It is satisfiable iff the formula is, and replacing the values of the fixed
+///variables in any of its models by the fixed literals gives a model of the formula.
+///If propagation reaches a conflict the reduced formula contains the empty clause.
+pub fn preprocess(formula: &CnfFormula) -> (CnfFormula, Vec<Lit>) {
+    let mut reduced = CnfFormula::from(formula.iter());
+    reduced.set_var_count(formula.var_count());
+    let mut fixed = Vec::new();
+    loop {
+        let (forced, conflict) = propagate_units(&reduced);
+        if conflict {
+            let mut unsat = CnfFormula::new();
+            unsat.set_var_count(formula.var_count());
+            unsat.add_clause(&[]);
+            return (unsat, fixed);
+        }
+        reduced = assign(&reduced, &forced);
+        let pure = pure_literals(&reduced);
+        reduced = assign(&reduced, &pure);
+        if forced.is_empty() && pure.is_empty() {
+            return (reduced, fixed);
+        }
+        fixed.extend(forced);
+        fixed.extend(pure);
+    }
+}
+///Returns the groups of literals that binary clauses alone prove equivalent
+///These are the strongly connected components of the implication graph with more than
+///one literal. Of a group and the group of its negations only the one whose smallest
+///variable appears positive is returned. Literals within a group are sorted, groups are
+///ordered by their first literal. A group containing a literal together with its negation
+///proves the formula unsatisfiable.
+pub fn equivalent_literals(formula: &CnfFormula) -> Vec<Vec<Lit>> {
+    let nodes = 2 * formula.var_count();
+    let mut successors = vec![Vec::new(); nodes];
+    for (from, to) in implication_edges(formula) {
+        successors[from.code()].push(to.code());
+    }
+    //Tarjan's algorithm, with an explicit stack of nodes and their next successor
+    let mut order = vec![usize::MAX; nodes];
+    let mut low = vec![0; nodes];
+    let mut on_stack = vec![false; nodes];
+    let mut stack = Vec::new();
+    let mut visit: Vec<(usize, usize)> = Vec::new();
+    let mut next_order = 0;
+    let mut groups = Vec::new();
+    for root in 0..nodes {
+        if order[root] != usize::MAX {
+            continue;
+        }
+        visit.push((root, 0));
+        while let Some(&mut (node, ref mut next)) = visit.last_mut() {
+            if *next == 0 {
+                order[node] = next_order;
+                low[node] = next_order;
+                next_order += 1;
+                stack.push(node);
+                on_stack[node] = true;
+            }
+            if let Some(&succ) = successors[node].get(*next) {
+                *next += 1;
+                if order[succ] == usize::MAX {
+                    visit.push((succ, 0));
+                } else if on_stack[succ] {
+                    low[node] = low[node].min(order[succ]);
+                }
+                continue;
+            }
+            visit.pop();
+            if let Some(&(parent, _)) = visit.last() {
+                low[parent] = low[parent].min(low[node]);
+            }
+            if low[node] == order[node] {
+                let mut group = Vec::new();
+                loop {
+                    let member = stack.pop().unwrap();
+                    on_stack[member] = false;
+                    group.push(Lit::from_code(member));
+                    if member == node {
+                        break;
+                    }
+                }
+                group.sort();
+                if group.len() > 1 && group[0].is_positive() {
+                    groups.push(group);
+                }
+            }
+        }
+    }
+    groups.sort();
+    groups
+}
+///Adds the binary clauses for the implications following from chains of at most depth
+///binary clauses, skipping those already in the formula
+///The implication graph is the one of implication_edges over the clauses before any are
+///added. Chains leading from a literal back to itself or to its negation are skipped, so
+///only binary clauses over two distinct variables are added.
+///Adds no auxiliary variables
+pub fn add_transitive_implications(formula: &mut CnfFormula, depth: usize) {
+    let nodes = 2 * formula.var_count();
+    let mut successors = vec![Vec::new(); nodes];
+    for (from, to) in implication_edges(formula) {
+        successors[from.code()].push(to.code());
+    }
+    //The clause !a | c stands for both a -> c and !c -> !a
+    let clause_key = |a: Lit, c: Lit| if !a < c { (!a, c) } else { (c, !a) };
+    let mut present: HashSet<(Lit, Lit)> = formula
+        .iter()
+        .filter(|clause| clause.len() == 2)
+        .map(|clause| clause_key(!clause[0], clause[1]))
+        .collect();
+    //Only the nodes a search reached are scanned and reset, so each search costs
+    //the size of the part of the graph within depth of its root
+    let mut visited = vec![false; nodes];
+    let mut reached = Vec::new();
+    for root in 0..nodes {
+        visited[root] = true;
+        reached.push(root);
+        let mut frontier_start = 0;
+        for _ in 0..depth {
+            let frontier_end = reached.len();
+            if frontier_start == frontier_end {
+                break;
+            }
+            for i in frontier_start..frontier_end {
+                for &succ in &successors[reached[i]] {
+                    if !visited[succ] {
+                        visited[succ] = true;
+                        reached.push(succ);
+                    }
+                }
+            }
+            frontier_start = frontier_end;
+        }
+        let a = Lit::from_code(root);
+        for node in reached.drain(..) {
+            visited[node] = false;
+            let c = Lit::from_code(node);
+            if c.index() != a.index() && present.insert(clause_key(a, c)) {
+                formula.add_clause(&[!a, c]);
+            }
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use varisat::{CnfFormula, ExtendFormula, Lit, Solver};
+
+    use crate::{
+        add_exactly_one, add_transitive_implications, equivalent_literals, is_model_valid,
+        preprocess, propagate_units, pure_literals, quick_unsat_check, remove_subsumed, simplify,
+    };
+    fn solve(formula: &CnfFormula) -> bool {
+        let mut solver = Solver::new();
+        solver.add_formula(formula);
+        solver.solve().unwrap()
+    }
+    #[test]
+    fn propagate_chain() {
+        let mut formula = CnfFormula::new();
+        let (a, b, c) = formula.new_lits();
+        formula.add_clause(&[!a, b]);
+        formula.add_clause(&[a]);
+        formula.add_clause(&[!b, c, a]);
+        assert_eq!(propagate_units(&formula), (vec![a, b], false));
+        formula.add_clause(&[!b, !c]);
+        assert_eq!(propagate_units(&formula), (vec![a, b, !c], false));
+    }
+    #[test]
+    fn propagate_conflict() {
+        let mut formula = CnfFormula::new();
+        let a = formula.new_lit();
+        formula.add_clause(&[a]);
+        formula.add_clause(&[!a]);
+        assert!(propagate_units(&formula).1);
+        let mut formula = CnfFormula::new();
+        formula.add_clause(&[]);
+        assert_eq!(propagate_units(&formula), (vec![], true));
+    }
+    #[test]
+    fn pure_literal_detection() {
+        let mut formula = CnfFormula::new();
+        let (a, b, c, d) = formula.new_lits();
+        formula.add_clause(&[a, b]);
+        formula.add_clause(&[a, !b, !c]);
+        formula.add_clause(&[b, !c]);
+        assert_eq!(pure_literals(&formula), vec![a, !c]);
+        formula.add_clause(&[!a, d]);
+        assert_eq!(pure_literals(&formula), vec![!c, d]);
+    }
+    #[test]
+    fn subsumed_clauses() {
+        let mut formula = CnfFormula::new();
+        let (a, b, c) = formula.new_lits();
+        formula.add_clause(&[b, a]);
+        formula.add_clause(&[a]);
+        formula.add_clause(&[!a, c, b]);
+        formula.add_clause(&[c, !a]);
+        formula.add_clause(&[!a, c]);
+        let reduced = remove_subsumed(&formula);
+        let clauses: Vec<&[Lit]> = reduced.iter().collect();
+        assert_eq!(clauses, vec![&[a][..], &[!a, c]]);
+        assert_eq!(solve(&reduced), solve(&formula));
+        formula.add_clause(&[!c]);
+        let reduced = remove_subsumed(&formula);
+        assert_eq!(reduced.len(), 3);
+        assert!(!solve(&reduced));
+    }
+    #[test]
+    fn subsumption_keeps_models() {
+        let mut formula = CnfFormula::new();
+        let lits: Vec<Lit> = formula.new_lit_iter(12).collect();
+        add_exactly_one(&mut formula, &lits);
+        add_exactly_one(&mut formula, &lits[3..9]);
+        formula.add_clause(&[
+            lits[0], lits[3], lits[4], lits[5], lits[6], lits[7], lits[8],
+        ]);
+        //The unit clause on each top commander subsumes the clauses implying it
+        let reduced = remove_subsumed(&formula);
+        assert!(reduced.len() < formula.len());
+        assert!(solve(&reduced));
+        let mut solver = Solver::new();
+        solver.add_formula(&reduced);
+        solver.assume(&[lits[1]]);
+        assert!(!solver.solve().unwrap());
+    }
+    #[test]
+    fn simplify_tautology() {
+        let mut formula = CnfFormula::new();
+        let lits: Vec<Lit> = formula.new_lit_iter(6).collect();
+        add_exactly_one(&mut formula, &lits);
+        formula.add_clause(&[lits[0], lits[3], !lits[0]]);
+        let simplified = simplify(&formula);
+        assert_eq!(simplified.len(), formula.len() - 1);
+        assert_eq!(simplified.var_count(), formula.var_count());
+        assert!(solve(&simplified));
+        formula.add_clause(&[lits[1]]);
+        formula.add_clause(&[lits[2]]);
+        assert!(!solve(&simplify(&formula)));
+    }
+    #[test]
+    fn simplify_duplicates() {
+        let mut formula = CnfFormula::new();
+        let (a, b, c) = formula.new_lits();
+        formula.add_clause(&[a, b, a]);
+        formula.add_clause(&[b, a]);
+        formula.add_clause(&[c]);
+        formula.add_clause(&[]);
+        let simplified = simplify(&formula);
+        let clauses: Vec<&[Lit]> = simplified.iter().collect();
+        assert_eq!(clauses, vec![&[a, b][..], &[c], &[]]);
+    }
+    #[test]
+    fn preprocess_reapply() {
+        let mut formula = CnfFormula::new();
+        let lits: Vec<Lit> = formula.new_lit_iter(8).collect();
+        //The first half is fixed by a chain of implications from a unit
+        formula.add_clause(&[lits[0]]);
+        for pair in lits[..4].windows(2) {
+            formula.add_clause(&[!pair[0], !pair[1]]);
+            formula.add_clause(&[pair[0], pair[1]]);
+        }
+        formula.add_clause(&[lits[1], lits[4], lits[5]]);
+        formula.add_clause(&[lits[4], !lits[5]]);
+        formula.add_clause(&[!lits[4], lits[5], lits[6]]);
+        formula.add_clause(&[!lits[6], !lits[7], lits[3]]);
+        formula.add_clause(&[lits[6], lits[7]]);
+        let (reduced, fixed) = preprocess(&formula);
+        assert!(reduced.len() < formula.len());
+        for lit in [lits[0], !lits[1], lits[2], !lits[3]] {
+            assert!(fixed.contains(&lit));
+        }
+        assert!(reduced
+            .iter()
+            .flatten()
+            .all(|lit| !fixed.iter().any(|fixed| fixed.index() == lit.index())));
+        let mut solver = Solver::new();
+        solver.add_formula(&reduced);
+        assert!(solver.solve().unwrap());
+        let mut model: Vec<Lit> = solver
+            .model()
+            .unwrap()
+            .into_iter()
+            .filter(|lit| !fixed.iter().any(|fixed| fixed.index() == lit.index()))
+            .collect();
+        model.extend_from_slice(&fixed);
+        assert!(is_model_valid(&formula, &model));
+    }
+    #[test]
+    fn preprocess_conflict() {
+        let mut formula = CnfFormula::new();
+        let (a, b) = formula.new_lits();
+        formula.add_clause(&[a]);
+        formula.add_clause(&[!a, b]);
+        formula.add_clause(&[!a, !b]);
+        let (reduced, _) = preprocess(&formula);
+        assert!(!solve(&reduced));
+    }
+    #[test]
+    fn equivalence_chain() {
+        let mut formula = CnfFormula::new();
+        let (a, b, c, d) = formula.new_lits();
+        for (x, y) in [(a, b), (b, c)] {
+            formula.add_clause(&[!x, y]);
+            formula.add_clause(&[x, !y]);
+        }
+        formula.add_clause(&[a, b, d]);
+        assert_eq!(equivalent_literals(&formula), vec![vec![a, b, c]]);
+        formula.add_clause(&[a, d]);
+        formula.add_clause(&[!c, !d]);
+        assert_eq!(equivalent_literals(&formula), vec![vec![a, b, c, !d]]);
+    }
+    #[test]
+    fn equivalence_contradiction() {
+        let mut formula = CnfFormula::new();
+        let (a, b, c) = formula.new_lits();
+        formula.add_clause(&[!a, b]);
+        formula.add_clause(&[!b, !a]);
+        formula.add_clause(&[a, b]);
+        formula.add_clause(&[a, !b]);
+        formula.add_clause(&[!c, a]);
+        let groups = equivalent_literals(&formula);
+        assert_eq!(groups, vec![vec![a, !a, b, !b]]);
+        assert!(!solve(&formula));
+    }
+    #[test]
+    fn transitive_chain() {
+        let mut formula = CnfFormula::new();
+        let (a, b, c, d) = formula.new_lits();
+        formula.add_clause(&[!a, b]);
+        formula.add_clause(&[!b, c]);
+        formula.add_clause(&[!c, d]);
+        let has_clause = |formula: &CnfFormula, expected: &[Lit]| {
+            formula.iter().any(|clause| {
+                let mut clause = clause.to_vec();
+                clause.sort();
+                clause == expected
+            })
+        };
+        let mut closed = CnfFormula::from(formula.iter());
+        closed.set_var_count(formula.var_count());
+        add_transitive_implications(&mut closed, 2);
+        assert!(has_clause(&closed, &[!a, c]));
+        assert!(has_clause(&closed, &[!b, d]));
+        assert!(!has_clause(&closed, &[!a, d]));
+        assert_eq!(closed.len(), formula.len() + 2);
+        //The added clauses shorten the chains, so repeating reaches further
+        add_transitive_implications(&mut closed, 2);
+        assert!(has_clause(&closed, &[!a, d]));
+        assert_eq!(closed.len(), formula.len() + 3);
+        add_transitive_implications(&mut closed, 2);
+        assert_eq!(closed.len(), formula.len() + 3);
+        let mut unbounded = CnfFormula::from(formula.iter());
+        unbounded.set_var_count(formula.var_count());
+        add_transitive_implications(&mut unbounded, usize::MAX);
+        assert!(has_clause(&unbounded, &[!a, d]));
+        assert_eq!(unbounded.len(), 6);
+        add_transitive_implications(&mut formula, 3);
+        assert!(has_clause(&formula, &[!a, d]));
+        assert_eq!(formula.len(), 6);
+    }
+    #[test]
+    fn quick_unsat() {
+        let mut formula = CnfFormula::new();
+        let lits: Vec<Lit> = formula.new_lit_iter(5).collect();
+        add_exactly_one(&mut formula, &lits);
+        assert!(!quick_unsat_check(&formula));
+        formula.add_clause(&[lits[0]]);
+        formula.add_clause(&[lits[3]]);
+        assert!(quick_unsat_check(&formula));
+        let mut formula = CnfFormula::new();
+        let a = formula.new_lit();
+        formula.add_clause(&[a]);
+        formula.add_clause(&[!a]);
+        assert!(quick_unsat_check(&formula));
+    }
+}
@@ -0,0 +1,413 @@
+use varisat::{CnfFormula, ExtendFormula, Lit};
+
+use crate::{add_at_most_k, add_at_most_one, add_equal, add_exactly_one, and_gate, or_gate};
+
+///Adds clauses requiring the true literals of the line to form exactly the given runs,
+///in order, separated by at least one false literal, as in a nonogram clue
+///Adds auxiliary variables: one "run starts here" literal per run and start position, plus those
+///of add_exactly_one per run
+pub fn add_run_sequence(formula: &mut CnfFormula, line: &[Lit], runs: &[usize]) {
+    assert!(runs.iter().all(|&len| len > 0), "runs must be non-empty");
+    let n = line.len();
+    let mut starts: Vec<Vec<(usize, Lit)>> = Vec::with_capacity(runs.len());
+    let mut earliest = 0;
+    for (r, &len) in runs.iter().enumerate() {
+        //Leave room for the later runs and one gap before each of them
+        let needed_after: usize = runs[r + 1..].iter().map(|len| len + 1).sum();
+        let latest = n.checked_sub(needed_after + len);
+        let run_starts: Vec<(usize, Lit)> = match latest {
+            Some(latest) => (earliest..=latest)
+                .map(|p| (p, formula.new_lit()))
+                .collect(),
+            None => Vec::new(),
+        };
+        let lits: Vec<Lit> = run_starts.iter().map(|&(_, lit)| lit).collect();
+        add_exactly_one(formula, &lits);
+        for &(p, start) in &run_starts {
+            for &cell in &line[p..p + len] {
+                formula.add_clause(&[!start, cell]);
+            }
+        }
+        starts.push(run_starts);
+        earliest += len + 1;
+    }
+    for r in 1..runs.len() {
+        //The next run must start after this one ends and a gap follows
+        for &(p, start) in &starts[r - 1] {
+            for &(q, next) in &starts[r] {
+                if q <= p + runs[r - 1] {
+                    formula.add_clause(&[!start, !next]);
+                }
+            }
+        }
+    }
+    for (i, &cell) in line.iter().enumerate() {
+        //A true cell must be covered by some run
+        let mut covering = vec![!cell];
+        for (r, run_starts) in starts.iter().enumerate() {
+            for &(p, start) in run_starts {
+                if p <= i && i < p + runs[r] {
+                    covering.push(start);
+                }
+            }
+        }
+        formula.add_clause(&covering);
+    }
+}
+///A deterministic finite automaton over the alphabet {false, true}
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dfa {
+    ///The number of states, states are numbered from 0
+    pub states: usize,
+    ///The state before any input is read
+    pub start: usize,
+    ///The successor of each state on a false and on a true input bit
+    pub transitions: Vec<[usize; 2]>,
+    ///The states in which the input is accepted
+    pub accepting: Vec<usize>,
+}
+///Adds clauses requiring the dfa to accept the input, read from first to last literal
+///Adds auxiliary variables: a one-hot state per step, input.len() + 1 of them
+pub fn add_dfa_accepts(formula: &mut CnfFormula, dfa: &Dfa, input: &[Lit]) {
+    assert!(
+        dfa.transitions.len() == dfa.states,
+        "one transition pair per state"
+    );
+    assert!(
+        dfa.start < dfa.states
+            && dfa.transitions.iter().flatten().all(|&q| q < dfa.states)
+            && dfa.accepting.iter().all(|&q| q < dfa.states),
+        "state out of range"
+    );
+    let steps: Vec<Vec<Lit>> = (0..=input.len())
+        .map(|_| formula.new_lit_iter(dfa.states).collect())
+        .collect();
+    for step in &steps {
+        add_exactly_one(formula, step);
+    }
+    formula.add_clause(&[steps[0][dfa.start]]);
+    for (t, &bit) in input.iter().enumerate() {
+        for (q, &[on_false, on_true]) in dfa.transitions.iter().enumerate() {
+            formula.add_clause(&[!steps[t][q], bit, steps[t + 1][on_false]]);
+            formula.add_clause(&[!steps[t][q], !bit, steps[t + 1][on_true]]);
+        }
+    }
+    let end = &steps[input.len()];
+    let accepted: Vec<Lit> = dfa.accepting.iter().map(|&q| end[q]).collect();
+    formula.add_clause(&accepted);
+}
+///Returns a literal that is true iff the sequence never goes from true to false,
+///meaning each true literal is followed only by true literals
+///Adds auxiliary variables: one per step and the returned literal
+pub fn reified_monotone(formula: &mut CnfFormula, vars: &[Lit]) -> Lit {
+    let steps: Vec<Lit> = vars
+        .windows(2)
+        .map(|pair| or_gate(formula, &[!pair[0], pair[1]]))
+        .collect();
+    and_gate(formula, &steps)
+}
+///Adds clauses allowing at most b maximal runs of consecutive true literals
+///Adds auxiliary variables: one "run starts here" literal per position after the first,
+///plus those of add_at_most_k
+pub fn add_at_most_runs(formula: &mut CnfFormula, vars: &[Lit], b: usize) {
+    let starts: Vec<Lit> = vars
+        .iter()
+        .enumerate()
+        .map(|(i, &var)| {
+            if i == 0 {
+                var
+            } else {
+                and_gate(formula, &[var, !vars[i - 1]])
+            }
+        })
+        .collect();
+    add_at_most_k(formula, &starts, b);
+}
+///Adds clauses requiring every maximal run of consecutive true literals to have a length
+///from min_run to max_run, a min_run of 0 leaves the length unbounded below
+///Adds auxiliary variables: one "run starts here" and one "run ends here" literal per
+///position, except at the boundaries where the literal itself is used
+pub fn add_run_length_bounds(
+    formula: &mut CnfFormula,
+    vars: &[Lit],
+    min_run: usize,
+    max_run: usize,
+) {
+    let n = vars.len();
+    let starts: Vec<Lit> = (0..n)
+        .map(|i| {
+            if i == 0 {
+                vars[i]
+            } else {
+                and_gate(formula, &[vars[i], !vars[i - 1]])
+            }
+        })
+        .collect();
+    let ends: Vec<Lit> = (0..n)
+        .map(|i| {
+            if i + 1 == n {
+                vars[i]
+            } else {
+                and_gate(formula, &[vars[i], !vars[i + 1]])
+            }
+        })
+        .collect();
+    for (i, &start) in starts.iter().enumerate() {
+        //A run starting at i covers at least min_run literals, running into the end
+        //of the sequence forbids the start
+        if i + min_run > n {
+            formula.add_clause(&[!start]);
+        } else {
+            for &var in &vars[i..i + min_run] {
+                formula.add_clause(&[!start, var]);
+            }
+        }
+        //and ends within max_run literals
+        let mut clause = vec![!start];
+        clause.extend_from_slice(&ends[i..n.min(i + max_run)]);
+        formula.add_clause(&clause);
+    }
+}
+///Adds clauses requiring b to be a reversed, so a[i] equals b[n - 1 - i]
+///Passing the same sequence twice makes it a palindrome.
+///Adds no auxiliary variables
+pub fn add_reversed(formula: &mut CnfFormula, a: &[Lit], b: &[Lit]) {
+    assert!(a.len() == b.len(), "sequences must have the same length");
+    for (&a, &b) in a.iter().zip(b.iter().rev()) {
+        add_equal(formula, a, b);
+    }
+}
+///Adds clauses keeping tasks from overlapping on a timeline of horizon slots
+///task_starts[t][s] is true iff task t starts at slot s, it then occupies slots s up to
+///s + durations[t] - 1, which must all lie within the horizon.
+///The start literals are not required to be one-hot by this function.
+///Adds auxiliary variables: one "task occupies this slot" literal per task and slot
+pub fn add_no_overlap(
+    formula: &mut CnfFormula,
+    task_starts: &[&[Lit]],
+    durations: &[usize],
+    horizon: usize,
+) {
+    assert!(
+        task_starts.len() == durations.len(),
+        "one duration per task"
+    );
+    let mut occupied = vec![Vec::with_capacity(task_starts.len()); horizon];
+    for (&starts, &duration) in task_starts.iter().zip(durations) {
+        for (s, &start) in starts.iter().enumerate() {
+            if s + duration > horizon {
+                formula.add_clause(&[!start]);
+            }
+        }
+        for (slot, occupants) in occupied.iter_mut().enumerate() {
+            let covering: Vec<Lit> = starts
+                .iter()
+                .enumerate()
+                .filter(|&(s, _)| s <= slot && slot < s + duration)
+                .map(|(_, &start)| start)
+                .collect();
+            occupants.push(or_gate(formula, &covering));
+        }
+    }
+    for occupants in &occupied {
+        add_at_most_one(formula, occupants);
+    }
+}
+#[cfg(test)]
+mod tests {
+    use varisat::{CnfFormula, ExtendFormula, Lit, Solver};
+
+    use crate::{
+        add_at_most_runs, add_dfa_accepts, add_exactly_one, add_no_overlap, add_reversed,
+        add_run_length_bounds, add_run_sequence, reified_monotone, Dfa,
+    };
+    fn all_lines(formula: &CnfFormula, line: &[Lit]) -> Vec<String> {
+        let mut solver = Solver::new();
+        solver.add_formula(formula);
+        let mut lines = Vec::new();
+        while solver.solve().unwrap() {
+            let model = solver.model().unwrap();
+            let values: Vec<bool> = line.iter().map(|l| model.contains(l)).collect();
+            lines.push(values.iter().map(|&v| if v { '1' } else { '0' }).collect());
+            let blocking: Vec<Lit> = line
+                .iter()
+                .zip(&values)
+                .map(|(&l, &v)| if v { !l } else { l })
+                .collect();
+            solver.add_clause(&blocking);
+        }
+        lines.sort();
+        lines
+    }
+    #[test]
+    fn dfa_even_ones() {
+        let dfa = Dfa {
+            states: 2,
+            start: 0,
+            transitions: vec![[0, 1], [1, 0]],
+            accepting: vec![0],
+        };
+        let mut formula = CnfFormula::new();
+        let input: Vec<Lit> = formula.new_lit_iter(4).collect();
+        add_dfa_accepts(&mut formula, &dfa, &input);
+        let accepted = all_lines(&formula, &input);
+        assert_eq!(accepted.len(), 8);
+        for line in accepted {
+            assert_eq!(line.matches('1').count() % 2, 0);
+        }
+    }
+    #[test]
+    fn dfa_empty_input() {
+        let dfa = Dfa {
+            states: 2,
+            start: 1,
+            transitions: vec![[0, 1], [1, 0]],
+            accepting: vec![0],
+        };
+        let mut formula = CnfFormula::new();
+        add_dfa_accepts(&mut formula, &dfa, &[]);
+        assert!(all_lines(&formula, &[]).is_empty());
+    }
+    #[test]
+    fn monotone_flag() {
+        for (pattern, monotone) in [
+            ("0011", true),
+            ("0000", true),
+            ("1111", true),
+            ("0101", false),
+            ("1000", false),
+            ("0110", false),
+        ] {
+            let mut formula = CnfFormula::new();
+            let vars: Vec<Lit> = formula.new_lit_iter(4).collect();
+            let flag = reified_monotone(&mut formula, &vars);
+            for (&var, c) in vars.iter().zip(pattern.chars()) {
+                formula.add_clause(&[if c == '1' { var } else { !var }]);
+            }
+            formula.add_clause(&[if monotone { !flag } else { flag }]);
+            assert!(all_lines(&formula, &vars).is_empty(), "{}", pattern);
+        }
+    }
+    #[test]
+    fn runs_2_1() {
+        let mut formula = CnfFormula::new();
+        let line: Vec<Lit> = formula.new_lit_iter(5).collect();
+        add_run_sequence(&mut formula, &line, &[2, 1]);
+        assert_eq!(all_lines(&formula, &line), vec!["01101", "11001", "11010"]);
+    }
+    #[test]
+    fn runs_exact_fit() {
+        let mut formula = CnfFormula::new();
+        let line: Vec<Lit> = formula.new_lit_iter(6).collect();
+        add_run_sequence(&mut formula, &line, &[1, 2, 1]);
+        assert_eq!(all_lines(&formula, &line), vec!["101101"]);
+    }
+    #[test]
+    fn runs_empty_and_too_long() {
+        let mut formula = CnfFormula::new();
+        let line: Vec<Lit> = formula.new_lit_iter(3).collect();
+        add_run_sequence(&mut formula, &line, &[]);
+        assert_eq!(all_lines(&formula, &line), vec!["000"]);
+        let mut formula = CnfFormula::new();
+        let line: Vec<Lit> = formula.new_lit_iter(3).collect();
+        add_run_sequence(&mut formula, &line, &[2, 1]);
+        assert!(all_lines(&formula, &line).is_empty());
+    }
+    #[test]
+    fn at_most_one_run() {
+        let mut formula = CnfFormula::new();
+        let line: Vec<Lit> = formula.new_lit_iter(6).collect();
+        add_at_most_runs(&mut formula, &line, 1);
+        let lines = all_lines(&formula, &line);
+        assert!(lines.contains(&"111000".to_string()));
+        assert!(!lines.contains(&"110011".to_string()));
+        //The empty line and one run for each start and end
+        assert_eq!(lines.len(), 1 + 6 * 7 / 2);
+        let mut formula = CnfFormula::new();
+        let line: Vec<Lit> = formula.new_lit_iter(6).collect();
+        add_at_most_runs(&mut formula, &line, 2);
+        assert!(all_lines(&formula, &line).contains(&"110011".to_string()));
+    }
+    #[test]
+    fn run_length_bounds() {
+        for (min_run, max_run) in [(2, 3), (0, 2), (1, 6), (3, 1)] {
+            let mut formula = CnfFormula::new();
+            let line: Vec<Lit> = formula.new_lit_iter(6).collect();
+            add_run_length_bounds(&mut formula, &line, min_run, max_run);
+            let mut expected: Vec<String> = (0..1u32 << 6)
+                .map(|bits| {
+                    (0..6)
+                        .map(|i| if bits >> i & 1 == 1 { '1' } else { '0' })
+                        .collect()
+                })
+                .filter(|line: &String| {
+                    line.split('0')
+                        .filter(|run| !run.is_empty())
+                        .all(|run| min_run <= run.len() && run.len() <= max_run)
+                })
+                .collect();
+            expected.sort();
+            assert_eq!(all_lines(&formula, &line), expected);
+        }
+        let mut formula = CnfFormula::new();
+        let line: Vec<Lit> = formula.new_lit_iter(6).collect();
+        add_run_length_bounds(&mut formula, &line, 2, 3);
+        let lines = all_lines(&formula, &line);
+        assert!(lines.contains(&"110110".to_string()));
+        assert!(!lines.contains(&"101000".to_string()));
+    }
+    #[test]
+    fn reversed() {
+        let mut formula = CnfFormula::new();
+        let a: Vec<Lit> = formula.new_lit_iter(5).collect();
+        let b: Vec<Lit> = formula.new_lit_iter(5).collect();
+        add_reversed(&mut formula, &a, &b);
+        for (&lit, c) in a.iter().zip("10110".chars()) {
+            formula.add_clause(&[if c == '1' { lit } else { !lit }]);
+        }
+        assert_eq!(all_lines(&formula, &b), vec!["01101"]);
+    }
+    #[test]
+    fn palindrome() {
+        let mut formula = CnfFormula::new();
+        let line: Vec<Lit> = formula.new_lit_iter(5).collect();
+        add_reversed(&mut formula, &line, &line);
+        let lines = all_lines(&formula, &line);
+        assert_eq!(lines.len(), 8);
+        assert!(lines
+            .iter()
+            .all(|line| line.chars().rev().collect::<String>() == *line));
+    }
+    fn schedule(horizon: usize, durations: &[usize]) -> Option<Vec<usize>> {
+        let mut formula = CnfFormula::new();
+        let starts: Vec<Vec<Lit>> = durations
+            .iter()
+            .map(|_| formula.new_lit_iter(horizon).collect())
+            .collect();
+        for task in &starts {
+            add_exactly_one(&mut formula, task);
+        }
+        let slices: Vec<&[Lit]> = starts.iter().map(|task| &task[..]).collect();
+        add_no_overlap(&mut formula, &slices, durations, horizon);
+        let mut solver = Solver::new();
+        solver.add_formula(&formula);
+        if !solver.solve().unwrap() {
+            return None;
+        }
+        let model = solver.model().unwrap();
+        Some(
+            starts
+                .iter()
+                .map(|task| task.iter().position(|l| model.contains(l)).unwrap())
+                .collect(),
+        )
+    }
+    #[test]
+    fn no_overlap() {
+        assert!(schedule(3, &[2, 2]).is_none());
+        let starts = schedule(4, &[2, 2]).unwrap();
+        assert!(starts == vec![0, 2] || starts == vec![2, 0]);
+        assert!(schedule(4, &[5]).is_none());
+        assert_eq!(schedule(6, &[3, 1, 2]).map(|starts| starts.len()), Some(3));
+    }
+}
@@ -0,0 +1,627 @@
+use std::{collections::HashMap, fs::File, path::Path};
+
+use anyhow::anyhow;
+use varisat::{
+    checker::{CheckedProofStep, CheckerData, ProofProcessor},
+    CnfFormula, ExtendFormula, Lit, ProofFormat, Solver,
+};
+
+use crate::add_weighted_at_most;
+
+///A solver with a fixed formula loaded, queried repeatedly under different assumptions
+///Learned clauses are kept between queries
+pub struct Session<'a> {
+    solver: Solver<'a>,
+}
+impl<'a> Session<'a> {
+    ///Creates a session with the formula loaded
+    pub fn new(formula: &CnfFormula) -> Session<'a> {
+        let mut solver = Solver::new();
+        solver.add_formula(formula);
+        Session { solver }
+    }
+    ///Returns a model of the formula in which all assumptions hold,
+    ///or None if there is no such model
+    pub fn solve_under(&mut self, assumptions: &[Lit]) -> Option<Vec<Lit>> {
+        self.solver.assume(assumptions);
+        let res = self
+            .solver
+            .solve()
+            .expect("solving without proof processors cannot fail");
+        if res {
+            self.solver.model()
+        } else {
+            None
+        }
+    }
+}
+///A solver holding named constraints that can be switched on and off between queries
+///Each constraint is gated by an enable literal: all of its clauses are weakened by the
+///negated enable literal, which is assumed true only while the constraint is active.
+#[derive(Default)]
+pub struct ConstraintSolver<'a> {
+    solver: Solver<'a>,
+    var_count: usize,
+    enables: HashMap<String, Lit>,
+}
+impl<'a> ConstraintSolver<'a> {
+    ///Creates a solver without clauses or constraints
+    pub fn new() -> ConstraintSolver<'a> {
+        ConstraintSolver::default()
+    }
+    ///Returns a fresh literal for use in clauses and constraints
+    pub fn new_lit(&mut self) -> Lit {
+        self.var_count += 1;
+        Lit::from_index(self.var_count - 1, true)
+    }
+    ///Adds a clause that is active in every query
+    pub fn add_clause(&mut self, clause: &[Lit]) {
+        self.solver.add_clause(clause);
+    }
+    ///Adds the clauses added by build as a constraint that is only active when named
+    ///in a query. Auxiliary variables allocated by build never clash with literals from new_lit.
+    pub fn add_constraint(&mut self, name: &str, build: impl FnOnce(&mut CnfFormula)) {
+        assert!(
+            !self.enables.contains_key(name),
+            "duplicate constraint name {}",
+            name
+        );
+        let enable = self.new_lit();
+        let mut constraint = CnfFormula::new();
+        constraint.set_var_count(self.var_count);
+        build(&mut constraint);
+        self.var_count = constraint.var_count();
+        let mut gated = Vec::new();
+        for clause in constraint.iter() {
+            gated.clear();
+            gated.extend_from_slice(clause);
+            gated.push(!enable);
+            self.solver.add_clause(&gated);
+        }
+        self.enables.insert(name.to_string(), enable);
+    }
+    ///Returns a model in which exactly the named constraints are enforced,
+    ///or None if there is no such model
+    pub fn solve(&mut self, active_constraints: &[&str]) -> Option<Vec<Lit>> {
+        for name in active_constraints {
+            assert!(
+                self.enables.contains_key(*name),
+                "unknown constraint {}",
+                name
+            );
+        }
+        let mut enables: Vec<(&String, &Lit)> = self.enables.iter().collect();
+        enables.sort_unstable();
+        let assumptions: Vec<Lit> = enables
+            .into_iter()
+            .map(|(name, &enable)| {
+                if active_constraints.contains(&name.as_str()) {
+                    enable
+                } else {
+                    !enable
+                }
+            })
+            .collect();
+        self.solver.assume(&assumptions);
+        let res = self
+            .solver
+            .solve()
+            .expect("solving without proof processors cannot fail");
+        if res {
+            self.solver.model()
+        } else {
+            None
+        }
+    }
+}
+///The result of a solve that may give up before reaching an answer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SolveOutcome {
+    ///A model of the formula
+    Sat(Vec<Lit>),
+    ///The formula has no model
+    Unsat,
+    ///The budget ran out before the solver found an answer
+    Unknown,
+}
+//Aborts solving once the solver has learned more than budget clauses
+struct ConflictBudget {
+    budget: u64,
+    conflicts: u64,
+}
+impl ProofProcessor for ConflictBudget {
+    fn process_step(
+        &mut self,
+        step: &CheckedProofStep,
+        _data: CheckerData,
+    ) -> Result<(), anyhow::Error> {
+        if let CheckedProofStep::AtClause {
+            redundant: true, ..
+        } = step
+        {
+            self.conflicts += 1;
+            if self.conflicts > self.budget {
+                return Err(anyhow!("conflict budget exhausted"));
+            }
+        }
+        Ok(())
+    }
+}
+///Solves the formula, giving up with Unknown after max_conflicts conflicts
+///varisat has no interrupt callback, so the budget is enforced by a proof processor
+///counting learned clauses, one per conflict. This turns on varisat's proof checking,
+///which slows solving down. With no budget the formula is solved normally.
+pub fn solve_with_budget(formula: &CnfFormula, max_conflicts: Option<u64>) -> SolveOutcome {
+    let mut budget = max_conflicts.map(|budget| ConflictBudget {
+        budget,
+        conflicts: 0,
+    });
+    let mut solver = Solver::new();
+    if let Some(budget) = &mut budget {
+        solver.add_proof_processor(budget);
+    }
+    solver.add_formula(formula);
+    match solver.solve() {
+        Ok(true) => SolveOutcome::Sat(solver.model().unwrap()),
+        Ok(false) => SolveOutcome::Unsat,
+        Err(_) => SolveOutcome::Unknown,
+    }
+}
+///Solves the formula while writing a proof to the file at proof_path, replacing it
+///The proof is in the textual DRAT format, which external checkers such as drat-trim
+///accept together with the formula in DIMACS. It is written whatever the outcome, but only
+///proves anything when the outcome is Unsat. Errors creating or writing the file are
+///returned.
+pub fn solve_with_proof(
+    formula: &CnfFormula,
+    proof_path: &Path,
+) -> Result<SolveOutcome, anyhow::Error> {
+    let mut solver = Solver::new();
+    //varisat requires the proof target before any clauses are added
+    solver.write_proof(File::create(proof_path)?, ProofFormat::Drat);
+    solver.add_formula(formula);
+    let outcome = if solver.solve()? {
+        SolveOutcome::Sat(solver.model().unwrap())
+    } else {
+        SolveOutcome::Unsat
+    };
+    solver.close_proof()?;
+    Ok(outcome)
+}
+///Returns a model of the formula that agrees with as many of the hints as was easy to
+///keep, or None if the formula is unsatisfiable
+///varisat has no way to set preferred phases, so the hints are assumed instead. When the
+///assumptions fail, the hints in the failed core are dropped and solving is repeated.
+///A model satisfying all hints is returned whenever one exists.
+pub fn solve_with_hints(formula: &CnfFormula, hints: &[Lit]) -> Option<Vec<Lit>> {
+    let mut solver = Solver::new();
+    solver.add_formula(formula);
+    let mut hints = hints.to_vec();
+    loop {
+        solver.assume(&hints);
+        if solver
+            .solve()
+            .expect("solving without proof processors cannot fail")
+        {
+            return solver.model();
+        }
+        let core = solver.failed_core().unwrap_or(&[]);
+        if core.is_empty() {
+            return None;
+        }
+        let core = core.to_vec();
+        hints.retain(|hint| !core.contains(hint));
+    }
+}
+///Returns for each input whether it is true in the model
+///Inputs missing from the model count as false
+pub fn project_model(inputs: &[Lit], model: &[Lit]) -> Vec<bool> {
+    inputs.iter().map(|lit| model.contains(lit)).collect()
+}
+///Returns the number of variables whose value in the model differs from the target
+///Variables missing from the model count as false
+pub fn hamming_distance(vars: &[Lit], model: &[Lit], target: &[bool]) -> usize {
+    assert!(vars.len() == target.len(), "one target value per variable");
+    project_model(vars, model)
+        .iter()
+        .zip(target)
+        .filter(|(value, target)| value != target)
+        .count()
+}
+///Adds a clause excluding the assignment the model gives the projection literals
+///Projection literals missing from the model count as false.
+///Adds no auxiliary variables
+pub fn add_blocking_clause(formula: &mut impl ExtendFormula, projection: &[Lit], model: &[Lit]) {
+    let blocking: Vec<Lit> = projection
+        .iter()
+        .map(|&lit| if model.contains(&lit) { !lit } else { lit })
+        .collect();
+    formula.add_clause(&blocking);
+}
+///Returns the distinct assignments of the projection literals over the models of the
+///formula, stopping after limit of them if given
+///Each assignment is aligned with the projection. After each model a clause blocking
+///its projected assignment is added, so models differing only outside the projection
+///are returned once.
+pub fn all_models(
+    formula: &CnfFormula,
+    projection: &[Lit],
+    limit: Option<usize>,
+) -> Vec<Vec<bool>> {
+    let mut solver = Solver::new();
+    solver.add_formula(formula);
+    //No limit stops only once the projected assignments run out
+    let limit = limit.unwrap_or(usize::MAX);
+    let mut models = Vec::new();
+    while models.len() < limit
+        && solver
+            .solve()
+            .expect("solving without proof processors cannot fail")
+    {
+        let model = solver.model().unwrap();
+        add_blocking_clause(&mut solver, projection, &model);
+        models.push(project_model(projection, &model));
+    }
+    models
+}
+///Returns the number of distinct assignments of the projection over the models of the formula
+///This enumerates them with all_models, so it is only practical for small counts.
+pub fn count_models(formula: &CnfFormula, projection: &[Lit]) -> usize {
+    all_models(formula, projection, None).len()
+}
+///How many models a formula has up to a projection, as far as solution_multiplicity looked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Multiplicity {
+    ///The formula is unsatisfiable
+    None,
+    ///The formula has exactly this many distinct projected models
+    Exactly(usize),
+    ///The search stopped after finding this many, there may be more
+    AtLeast(usize),
+}
+///Returns how many distinct assignments of the projection the models of the formula have,
+///looking for at most limit of them
+///A limit of 2 is enough to tell unique solutions from under-constrained formulas.
+pub fn solution_multiplicity(
+    formula: &CnfFormula,
+    projection: &[Lit],
+    limit: usize,
+) -> Multiplicity {
+    assert!(limit > 0, "the limit must allow at least one model");
+    match all_models(formula, projection, Some(limit)).len() {
+        0 => Multiplicity::None,
+        count if count == limit => Multiplicity::AtLeast(count),
+        count => Multiplicity::Exactly(count),
+    }
+}
+///Returns whether the formula has exactly one model up to the projection
+///An unsatisfiable formula has no unique solution.
+pub fn has_unique_solution(formula: &CnfFormula, projection: &[Lit]) -> bool {
+    let mut solver = Solver::new();
+    solver.add_formula(formula);
+    if !solver
+        .solve()
+        .expect("solving without proof processors cannot fail")
+    {
+        return false;
+    }
+    let model = solver.model().unwrap();
+    add_blocking_clause(&mut solver, projection, &model);
+    !solver
+        .solve()
+        .expect("solving without proof processors cannot fail")
+}
+///Returns whether every clause of the formula has a literal that is true in the model
+///Variables missing from the model satisfy no literal
+pub fn is_model_valid(formula: &CnfFormula, model: &[Lit]) -> bool {
+    let mut values = vec![None; formula.var_count()];
+    for &lit in model {
+        if lit.index() < values.len() {
+            values[lit.index()] = Some(lit.is_positive());
+        }
+    }
+    formula.iter().all(|clause| {
+        clause
+            .iter()
+            .any(|lit| values[lit.index()] == Some(lit.is_positive()))
+    })
+}
+///Returns the minimum total weight of violated soft clauses over the models of the hard
+///clauses, together with a model achieving it
+///Each soft clause gets a relaxation literal. After each model a BDD bound requiring the
+///relaxed weight to be below the model's cost is added, until no cheaper model exists.
+///The returned model only covers the variables of the hard and soft clauses.
+///Panics if the hard clauses are unsatisfiable.
+pub fn solve_weighted_maxsat(hard: &CnfFormula, soft: &[(Vec<Lit>, u64)]) -> (u64, Vec<Lit>) {
+    let var_count = soft
+        .iter()
+        .flat_map(|(clause, _)| clause)
+        .map(|lit| lit.index() + 1)
+        .fold(hard.var_count(), usize::max);
+    let mut relaxed = CnfFormula::new();
+    relaxed.set_var_count(var_count);
+    let relax: Vec<Lit> = soft
+        .iter()
+        .map(|(clause, _)| {
+            let lit = relaxed.new_lit();
+            let mut clause = clause.clone();
+            clause.push(lit);
+            relaxed.add_clause(&clause);
+            lit
+        })
+        .collect();
+    let weights: Vec<u64> = soft.iter().map(|&(_, weight)| weight).collect();
+    let mut solver = Solver::new();
+    solver.add_formula(hard);
+    solver.add_formula(&relaxed);
+    let mut var_count_so_far = relaxed.var_count();
+    let mut best: Option<(u64, Vec<Lit>)> = None;
+    while solver
+        .solve()
+        .expect("solving without proof processors cannot fail")
+    {
+        let model = solver.model().unwrap();
+        let cost = soft
+            .iter()
+            .filter(|(clause, _)| !clause.iter().any(|lit| model.contains(lit)))
+            .map(|&(_, weight)| weight)
+            .sum();
+        let model = model
+            .into_iter()
+            .filter(|lit| lit.index() < var_count)
+            .collect();
+        best = Some((cost, model));
+        if cost == 0 {
+            break;
+        }
+        let mut bound = CnfFormula::new();
+        bound.set_var_count(var_count_so_far);
+        add_weighted_at_most(&mut bound, &relax, &weights, cost - 1);
+        var_count_so_far = bound.var_count();
+        solver.add_formula(&bound);
+    }
+    best.expect("hard clauses are unsatisfiable")
+}
+#[cfg(test)]
+mod tests {
+    use varisat::{CnfFormula, ExtendFormula, Lit};
+
+    use crate::{
+        add_at_least_k, add_at_most_one, add_at_most_one_pairwise, add_exactly_one, all_models,
+        count_models, exactly_k, hamming_distance, has_unique_solution, is_model_valid,
+        project_model, solution_multiplicity, solve_weighted_maxsat, solve_with_budget,
+        solve_with_hints, solve_with_proof, ConstraintSolver, Multiplicity, Session, SolveOutcome,
+    };
+    #[test]
+    fn session_assumptions() {
+        let mut formula = CnfFormula::new();
+        let lits: Vec<Lit> = formula.new_lit_iter(8).collect();
+        add_at_most_one(&mut formula, &lits);
+        let mut session = Session::new(&formula);
+        let model = session.solve_under(&[lits[2]]).unwrap();
+        assert!(model.contains(&lits[2]));
+        assert!(model.contains(&!lits[6]));
+        let model = session.solve_under(&[lits[6]]).unwrap();
+        assert!(model.contains(&lits[6]));
+        assert!(model.contains(&!lits[2]));
+        assert!(session.solve_under(&[lits[2], lits[6]]).is_none());
+        assert!(session.solve_under(&[]).is_some());
+    }
+    #[test]
+    fn toggled_constraints() {
+        let mut solver = ConstraintSolver::new();
+        let (a, b, c) = (solver.new_lit(), solver.new_lit(), solver.new_lit());
+        solver.add_clause(&[a, b]);
+        solver.add_clause(&[c]);
+        solver.add_constraint("not a", |formula| add_at_most_one(formula, &[a, c]));
+        solver.add_constraint("not b", |formula| add_at_most_one(formula, &[b, c]));
+        assert!(solver.solve(&[]).is_some());
+        let model = solver.solve(&["not a"]).unwrap();
+        assert!(model.contains(&!a) && model.contains(&b));
+        let model = solver.solve(&["not b"]).unwrap();
+        assert!(model.contains(&a) && model.contains(&!b));
+        assert!(solver.solve(&["not a", "not b"]).is_none());
+        assert!(solver.solve(&["not b"]).is_some());
+    }
+    #[test]
+    #[should_panic(expected = "unknown constraint")]
+    fn unknown_constraints() {
+        let mut solver = ConstraintSolver::new();
+        let a = solver.new_lit();
+        solver.add_constraint("a", |formula| formula.add_clause(&[a]));
+        solver.solve(&["zeta", "a", "alpha"]);
+    }
+    #[test]
+    fn projection_alignment() {
+        let mut formula = CnfFormula::new();
+        let lits: Vec<Lit> = formula.new_lit_iter(12).collect();
+        exactly_k(&mut formula, &lits, 3);
+        formula.add_clause(&[lits[1]]);
+        formula.add_clause(&[lits[10]]);
+        formula.add_clause(&[!lits[4]]);
+        formula.add_clause(&[lits[4], lits[7]]);
+        let model = Session::new(&formula).solve_under(&[]).unwrap();
+        let inputs = [lits[10], !lits[4], lits[7], lits[0], lits[1]];
+        assert_eq!(
+            project_model(&inputs, &model),
+            vec![true, true, true, false, true]
+        );
+    }
+    #[test]
+    fn model_validity() {
+        let mut formula = CnfFormula::new();
+        let lits: Vec<Lit> = formula.new_lit_iter(10).collect();
+        exactly_k(&mut formula, &lits, 4);
+        let mut model = Session::new(&formula).solve_under(&[]).unwrap();
+        assert!(is_model_valid(&formula, &model));
+        let flip = model
+            .iter()
+            .position(|&lit| lit == lits[0] || lit == !lits[0]);
+        model[flip.unwrap()] = !model[flip.unwrap()];
+        assert!(!is_model_valid(&formula, &model));
+        assert!(!is_model_valid(&formula, &[]));
+    }
+    #[test]
+    fn budget_trivial() {
+        let mut formula = CnfFormula::new();
+        let (a, b) = formula.new_lits();
+        formula.add_clause(&[a, b]);
+        formula.add_clause(&[!a]);
+        assert_eq!(
+            solve_with_budget(&formula, Some(10)),
+            SolveOutcome::Sat(vec![!a, b])
+        );
+        assert_eq!(
+            solve_with_budget(&formula, None),
+            SolveOutcome::Sat(vec![!a, b])
+        );
+        formula.add_clause(&[!b]);
+        assert_eq!(solve_with_budget(&formula, Some(10)), SolveOutcome::Unsat);
+    }
+    #[test]
+    fn budget_pigeonhole() {
+        //9 pigeons in 8 holes takes many conflicts to refute
+        let mut formula = CnfFormula::new();
+        let pigeons: Vec<Vec<Lit>> = (0..9).map(|_| formula.new_lit_iter(8).collect()).collect();
+        for pigeon in &pigeons {
+            formula.add_clause(pigeon);
+        }
+        for hole in 0..8 {
+            let column: Vec<Lit> = pigeons.iter().map(|pigeon| pigeon[hole]).collect();
+            add_at_most_one_pairwise(&mut formula, &column);
+        }
+        assert_eq!(solve_with_budget(&formula, Some(5)), SolveOutcome::Unknown);
+    }
+    #[test]
+    fn weighted_maxsat() {
+        let mut hard = CnfFormula::new();
+        let (a, b) = hard.new_lits();
+        //a and b are equal, so either both weight 1 clauses or the weight 5 clause is violated
+        hard.add_clause(&[!a, b]);
+        hard.add_clause(&[a, !b]);
+        let soft = vec![(vec![!a], 1), (vec![!b], 1), (vec![a], 5)];
+        let (cost, model) = solve_weighted_maxsat(&hard, &soft);
+        assert_eq!(cost, 2);
+        assert!(model.contains(&a) && model.contains(&b));
+        assert!(is_model_valid(&hard, &model));
+    }
+    #[test]
+    fn weighted_maxsat_free() {
+        let mut hard = CnfFormula::new();
+        let a = hard.new_lit();
+        let (cost, model) = solve_weighted_maxsat(&hard, &[(vec![!a], 3)]);
+        assert_eq!((cost, model), (0, vec![!a]));
+    }
+    #[test]
+    fn enumerate_one_hot() {
+        let mut formula = CnfFormula::new();
+        let lits: Vec<Lit> = formula.new_lit_iter(3).collect();
+        add_exactly_one(&mut formula, &lits);
+        let mut models = all_models(&formula, &lits, None);
+        models.sort();
+        assert_eq!(
+            models,
+            vec![
+                vec![false, false, true],
+                vec![false, true, false],
+                vec![true, false, false]
+            ]
+        );
+        assert_eq!(all_models(&formula, &lits, Some(2)).len(), 2);
+        assert_eq!(all_models(&formula, &lits[..1], None).len(), 2);
+        assert_eq!(all_models(&formula, &[], None), vec![Vec::<bool>::new()]);
+        assert_eq!(count_models(&formula, &lits), 3);
+    }
+    #[test]
+    fn distance_to_target() {
+        let mut formula = CnfFormula::new();
+        let lits: Vec<Lit> = formula.new_lit_iter(6).collect();
+        let target = [true, false, false, true, true, false];
+        for (&lit, &value) in lits.iter().zip(&target) {
+            formula.add_clause(&[if value { lit } else { !lit }]);
+        }
+        let model = Session::new(&formula).solve_under(&[]).unwrap();
+        assert_eq!(hamming_distance(&lits, &model, &target), 0);
+        let flipped: Vec<Lit> = model
+            .iter()
+            .map(|&lit| if lit.index() % 2 == 0 { !lit } else { lit })
+            .collect();
+        assert_eq!(hamming_distance(&lits, &flipped, &target), 3);
+    }
+    #[test]
+    fn hinted_models() {
+        let mut formula = CnfFormula::new();
+        let lits: Vec<Lit> = formula.new_lit_iter(10).collect();
+        exactly_k(&mut formula, &lits, 3);
+        let hints = [lits[1], !lits[2], lits[5], lits[9]];
+        let model = solve_with_hints(&formula, &hints).unwrap();
+        assert!(hints.iter().all(|hint| model.contains(hint)));
+        //Four true hints cannot all hold, the model still satisfies the formula
+        let hints = [lits[0], lits[3], lits[6], lits[8], !lits[1]];
+        let model = solve_with_hints(&formula, &hints).unwrap();
+        assert!(is_model_valid(&formula, &model));
+        formula.add_clause(&[]);
+        assert!(solve_with_hints(&formula, &hints).is_none());
+    }
+    #[test]
+    fn unique_solution() {
+        let mut formula = CnfFormula::new();
+        let lit = formula.new_lit();
+        add_exactly_one(&mut formula, &[lit]);
+        assert!(has_unique_solution(&formula, &[lit]));
+        let mut formula = CnfFormula::new();
+        let lits: Vec<Lit> = formula.new_lit_iter(3).collect();
+        add_exactly_one(&mut formula, &lits);
+        assert!(!has_unique_solution(&formula, &lits));
+        //Projected onto the first, it is true in one model and false in the others
+        assert!(!has_unique_solution(&formula, &lits[..1]));
+        formula.add_clause(&[]);
+        assert!(!has_unique_solution(&formula, &lits));
+    }
+    #[test]
+    fn multiplicity() {
+        let mut formula = CnfFormula::new();
+        let lits: Vec<Lit> = formula.new_lit_iter(3).collect();
+        add_exactly_one(&mut formula, &lits);
+        assert_eq!(
+            solution_multiplicity(&formula, &lits, 2),
+            Multiplicity::AtLeast(2)
+        );
+        assert_eq!(
+            solution_multiplicity(&formula, &lits, 10),
+            Multiplicity::Exactly(3)
+        );
+        formula.add_clause(&[lits[1]]);
+        assert_eq!(
+            solution_multiplicity(&formula, &lits, 2),
+            Multiplicity::Exactly(1)
+        );
+        formula.add_clause(&[!lits[1]]);
+        assert_eq!(
+            solution_multiplicity(&formula, &lits, 2),
+            Multiplicity::None
+        );
+    }
+    #[test]
+    fn unsat_proof() {
+        let path =
+            std::env::temp_dir().join(format!("varisat-utils-proof-{}.drat", std::process::id()));
+        let mut formula = CnfFormula::new();
+        let lits: Vec<Lit> = formula.new_lit_iter(6).collect();
+        add_exactly_one(&mut formula, &lits);
+        assert!(matches!(
+            solve_with_proof(&formula, &path).unwrap(),
+            SolveOutcome::Sat(_)
+        ));
+        add_at_least_k(&mut formula, &lits, 2);
+        assert_eq!(
+            solve_with_proof(&formula, &path).unwrap(),
+            SolveOutcome::Unsat
+        );
+        let proof = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(!proof.is_empty());
+        //A DRAT refutation ends by deriving the empty clause
+        assert!(proof.lines().any(|line| line.trim() == "0"));
+    }
+}
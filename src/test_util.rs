@@ -0,0 +1,33 @@
+use varisat::{CnfFormula, ExtendFormula, Lit, Solver};
+
+//Returns a model of the formula, or None if it is unsatisfiable
+pub fn solve(formula: &CnfFormula) -> Option<Vec<Lit>> {
+    let mut solver = Solver::new();
+    solver.add_formula(formula);
+    if solver.solve().unwrap() {
+        solver.model()
+    } else {
+        None
+    }
+}
+//Returns the value the gate is forced to for every assignment of the inputs
+pub fn truth_table(n: usize, build: impl Fn(&mut CnfFormula, &[Lit]) -> Lit) -> Vec<bool> {
+    (0..1 << n)
+        .map(|bits| {
+            let mut formula = CnfFormula::new();
+            let lits: Vec<Lit> = formula.new_lit_iter(n).collect();
+            let out = build(&mut formula, &lits);
+            for (i, &lit) in lits.iter().enumerate() {
+                formula.add_clause(&[if bits >> i & 1 == 1 { lit } else { !lit }]);
+            }
+            let mut solver = Solver::new();
+            solver.add_formula(&formula);
+            solver.assume(&[out]);
+            let can_be_true = solver.solve().unwrap();
+            solver.assume(&[!out]);
+            let can_be_false = solver.solve().unwrap();
+            assert!(can_be_true != can_be_false, "output not determined");
+            can_be_true
+        })
+        .collect()
+}
@@ -0,0 +1,47 @@
+use rand::{seq::index::sample, Rng};
+use varisat::{CnfFormula, ExtendFormula, Lit};
+
+use crate::add_exactly_one;
+
+///Returns a formula over universe variables made of num_groups overlapping exactly-one
+///constraints, each over between 1 and max_group distinct variables chosen at random
+///The instance only depends on the values drawn from rng: the same seeded generator
+///produces the same formula. Seeds are only reproducible across versions of rand if the
+///generator itself is portable (e.g. rand_chacha rather than StdRng).
+///Instances may be unsatisfiable.
+pub fn random_exactly_one_instance(
+    rng: &mut impl Rng,
+    num_groups: usize,
+    max_group: usize,
+    universe: usize,
+) -> CnfFormula {
+    assert!(universe > 0 && max_group > 0, "groups must be non-empty");
+    let mut formula = CnfFormula::new();
+    let lits: Vec<Lit> = formula.new_lit_iter(universe).collect();
+    for _ in 0..num_groups {
+        let size = rng.gen_range(1..=max_group.min(universe));
+        let group: Vec<Lit> = sample(rng, universe, size)
+            .into_iter()
+            .map(|i| lits[i])
+            .collect();
+        add_exactly_one(&mut formula, &group);
+    }
+    formula
+}
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+    use varisat::Solver;
+
+    use crate::random_exactly_one_instance;
+    #[test]
+    fn seeded_instance() {
+        let formula = random_exactly_one_instance(&mut StdRng::seed_from_u64(7), 10, 20, 200);
+        let again = random_exactly_one_instance(&mut StdRng::seed_from_u64(7), 10, 20, 200);
+        assert_eq!(formula, again);
+        assert!(formula.var_count() > 200);
+        let mut solver = Solver::new();
+        solver.add_formula(&formula);
+        solver.solve().unwrap();
+    }
+}